@@ -0,0 +1,75 @@
+//! Target acquisition of every unit at once, scanning every other unit against the spatial grid.
+//!
+//! Run with `cargo bench --bench spatial`. Units are spread over the ground of the shipped map,
+//! split between the two teams, and each one looks for the closest enemy in a creep's sight range.
+
+use std::{hint::black_box, time::Instant};
+
+use amoba::{map_data::MapData, spatial::SpatialGrid};
+use glam::Vec2;
+
+const SIGHT_RANGE: f32 = 10.;
+const FRAMES: u32 = 20;
+const CELL_SIZE: f32 = 2.5;
+
+fn main() {
+    let ground_size = MapData::load().ground_size;
+    println!("{:>6} {:>14} {:>14}", "units", "scan (µs)", "grid (µs)");
+
+    for count in [50, 100, 200, 400, 800] {
+        let units = spread_units(count, ground_size);
+
+        let scan = time_per_frame(|| {
+            for (position, team) in &units {
+                black_box(nearest_by_scan(&units, *position, *team));
+            }
+        });
+
+        let grid = time_per_frame(|| {
+            // rebuilt every frame, like the game does
+            let mut grid = SpatialGrid::new(ground_size, CELL_SIZE);
+            grid.extend(units.iter().copied());
+            for (position, team) in &units {
+                black_box(grid.nearest(*position, SIGHT_RANGE, |other| other != team));
+            }
+        });
+
+        println!("{count:>6} {scan:>14.1} {grid:>14.1}");
+    }
+}
+
+fn nearest_by_scan(units: &[(Vec2, u32)], position: Vec2, team: u32) -> Option<Vec2> {
+    units
+        .iter()
+        .filter(|(other, other_team)| {
+            *other_team != team && other.distance(position) <= SIGHT_RANGE
+        })
+        .map(|(other, _)| *other)
+        .min_by(|first, second| {
+            first
+                .distance_squared(position)
+                .total_cmp(&second.distance_squared(position))
+        })
+}
+
+fn time_per_frame(mut frame: impl FnMut()) -> f64 {
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        frame();
+    }
+    start.elapsed().as_secs_f64() * 1_000_000. / FRAMES as f64
+}
+
+// the same spread every run, no need for a random number crate
+fn spread_units(count: u32, ground_size: f32) -> Vec<(Vec2, u32)> {
+    let mut seed: u32 = 0x2545_f491;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed as f32 / u32::MAX as f32 - 0.5
+    };
+    (0..count)
+        .map(|unit| (Vec2::new(next(), next()) * ground_size, unit % 2))
+        .collect()
+}
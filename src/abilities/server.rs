@@ -0,0 +1,275 @@
+use ambient_api::{
+    components::core::{player::player, transform::translation},
+    prelude::*,
+};
+use amoba::{
+    roster::{AbilityDefinition, AbilityTargeting, HeroRoster},
+    status_effects::StatusKind,
+};
+
+#[path = "../server_common.rs"]
+mod server_common;
+use server_common::{deal_damage, is_alive, is_enemy, is_targetable};
+
+// skillshots leave from the hero's hands, not its feet
+const SKILLSHOT_LAUNCH_HEIGHT: f32 = 1.5;
+
+#[main]
+pub fn main() {
+    let roster = HeroRoster::load();
+
+    // a freshly picked hero starts with full mana and every ability ready
+    spawn_query((player(), components::hero_model(), components::role())).bind({
+        let roster = roster.clone();
+        move |players| {
+            for (player_id, (_, _, role)) in players {
+                let hero = match roster.hero(role) {
+                    Some(hero) => hero,
+                    None => continue,
+                };
+                entity::add_component(player_id, components::mana(), hero.mana);
+                entity::add_component(player_id, components::max_mana(), hero.mana);
+                entity::add_component(
+                    player_id,
+                    components::ability_cooldowns(),
+                    vec![0.0; hero.abilities.len()],
+                );
+            }
+        }
+    });
+
+    mana_and_cooldowns_system(roster.clone());
+
+    messages::CastAbility::subscribe(move |source, msg| {
+        // the ui module relays casts with the player who sent them, a client could name anyone
+        if source.local().is_none() {
+            return;
+        }
+        cast_ability(&roster, msg);
+    });
+
+    messages::MatchReset::subscribe(|_, _| {
+        for (player_id, _) in query(components::ability_cooldowns()).build().evaluate() {
+            entity::remove_component(player_id, components::mana());
+            entity::remove_component(player_id, components::max_mana());
+            entity::remove_component(player_id, components::ability_cooldowns());
+        }
+    });
+}
+
+fn mana_and_cooldowns_system(roster: HeroRoster) {
+    query((
+        player(),
+        components::role(),
+        components::mana(),
+        components::ability_cooldowns(),
+    ))
+    .each_frame(move |players| {
+        for (player_id, (_, role, mana, cooldowns)) in players {
+            let hero = roster.hero(role).unwrap();
+            let max_mana = entity::get_component(player_id, components::max_mana()).unwrap();
+            if mana < max_mana {
+                let mana = (mana + hero.mana_regen * delta_time()).min(max_mana);
+                entity::set_component(player_id, components::mana(), mana);
+            }
+
+            if cooldowns.iter().any(|cooldown| *cooldown > 0.0) {
+                let cooldowns = cooldowns
+                    .iter()
+                    .map(|cooldown| (cooldown - delta_time()).max(0.0))
+                    .collect();
+                entity::set_component(player_id, components::ability_cooldowns(), cooldowns);
+            }
+        }
+    });
+}
+
+fn cast_ability(roster: &HeroRoster, msg: messages::CastAbility) {
+    let player_id = msg.player_id;
+    let (model, role) = match (
+        entity::get_component(player_id, components::hero_model()),
+        entity::get_component(player_id, components::role()),
+    ) {
+        (Some(model), Some(role)) => (model, role),
+        _ => return,
+    };
+    if !is_alive(model) || entity::has_component(model, components::is_stunned()) {
+        return;
+    }
+    let ability = match roster
+        .hero(role)
+        .and_then(|hero| hero.abilities.get(msg.ability as usize))
+    {
+        Some(ability) => ability,
+        None => return,
+    };
+
+    let mut cooldowns = entity::get_component(player_id, components::ability_cooldowns()).unwrap();
+    let mana = entity::get_component(player_id, components::mana()).unwrap();
+    if cooldowns[msg.ability as usize] > 0.0 {
+        println!(
+            "{:?} tried to cast {} while on cooldown",
+            player_id, ability.name
+        );
+        return;
+    }
+    if mana < ability.mana_cost {
+        println!("{:?} has not enough mana for {}", player_id, ability.name);
+        return;
+    }
+
+    let team = entity::get_component(model, components::team()).unwrap();
+    let hero_position = entity::get_component(model, translation()).unwrap();
+
+    let cast = match ability.targeting {
+        AbilityTargeting::Unit => cast_on_unit(ability, model, team, hero_position, &msg),
+        AbilityTargeting::Area => cast_on_area(ability, model, team, hero_position, &msg),
+        AbilityTargeting::SelfBuff => cast_self_buff(ability, model),
+        AbilityTargeting::Skillshot => cast_skillshot(ability, model, team, hero_position, &msg),
+    };
+    if !cast {
+        return;
+    }
+
+    cooldowns[msg.ability as usize] = ability.cooldown;
+    entity::set_component(player_id, components::ability_cooldowns(), cooldowns);
+    entity::set_component(player_id, components::mana(), mana - ability.mana_cost);
+}
+
+// each cast_* returns false when the cursor was not on a valid target, so nothing is spent
+
+fn cast_on_unit(
+    ability: &AbilityDefinition,
+    model: EntityId,
+    team: u32,
+    hero_position: Vec3,
+    msg: &messages::CastAbility,
+) -> bool {
+    let target = match physics::raycast_first(msg.ray_origin, msg.ray_dir) {
+        Some(hit) => hit.entity,
+        None => return false,
+    };
+    if target == model || !is_enemy(team, target) || !is_targetable(target) {
+        return false;
+    }
+    let target_position = entity::get_component(target, translation()).unwrap();
+    if (target_position.xy() - hero_position.xy()).length() > ability.range {
+        return false;
+    }
+
+    deal_damage(target, ability.damage, model);
+    apply_status(ability, target, model);
+    true
+}
+
+fn cast_on_area(
+    ability: &AbilityDefinition,
+    model: EntityId,
+    team: u32,
+    hero_position: Vec3,
+    msg: &messages::CastAbility,
+) -> bool {
+    let center = match point_under_cursor(msg) {
+        Some(center) => center,
+        None => return false,
+    };
+    if (center.xy() - hero_position.xy()).length() > ability.range {
+        return false;
+    }
+
+    for (unit, (_, unit_position)) in query((components::health(), translation()))
+        .build()
+        .evaluate()
+    {
+        if is_enemy(team, unit)
+            && is_targetable(unit)
+            && (unit_position.xy() - center.xy()).length() <= ability.radius
+        {
+            deal_damage(unit, ability.damage, model);
+            apply_status(ability, unit, model);
+        }
+    }
+    true
+}
+
+fn cast_self_buff(ability: &AbilityDefinition, model: EntityId) -> bool {
+    if ability.heal > 0 {
+        let health = entity::get_component(model, components::health()).unwrap();
+        let max_health = entity::get_component(model, components::max_health()).unwrap();
+        entity::set_component(
+            model,
+            components::health(),
+            (health + ability.heal).min(max_health),
+        );
+    }
+    if ability.speed_bonus > 0.0 {
+        messages::ApplyStatusEffect::new(
+            model,
+            StatusKind::SpeedBoost as u32,
+            ability.speed_bonus,
+            ability.duration,
+            model,
+        )
+        .send_local_broadcast(false);
+    }
+    apply_status(ability, model, model);
+    true
+}
+
+fn cast_skillshot(
+    ability: &AbilityDefinition,
+    model: EntityId,
+    team: u32,
+    hero_position: Vec3,
+    msg: &messages::CastAbility,
+) -> bool {
+    let aim = match point_under_cursor(msg) {
+        Some(aim) => aim,
+        None => return false,
+    };
+    // always flies its whole range, wherever the cursor is along the way
+    let direction = (aim.xy() - hero_position.xy()).normalize_or_zero();
+    if direction == Vec2::ZERO {
+        return false;
+    }
+    let origin = hero_position + Vec3::Z * SKILLSHOT_LAUNCH_HEIGHT;
+    let destination = origin + (direction * ability.range).extend(0.0);
+
+    // the projectiles module puts the status effect on whatever the skillshot hits
+    let (status_kind, status_magnitude, status_duration) = match ability.status {
+        Some(status) => (status.kind as u32, status.magnitude, status.duration),
+        None => (0, 0.0, 0.0),
+    };
+    messages::SpawnSkillshot::new(
+        model,
+        origin,
+        destination,
+        ability.projectile_speed,
+        ability.damage,
+        team,
+        ability.radius,
+        status_kind,
+        status_magnitude,
+        status_duration,
+    )
+    .send_local_broadcast(false);
+    true
+}
+
+fn apply_status(ability: &AbilityDefinition, unit: EntityId, model: EntityId) {
+    if let Some(status) = ability.status {
+        messages::ApplyStatusEffect::new(
+            unit,
+            status.kind as u32,
+            status.magnitude,
+            status.duration,
+            model,
+        )
+        .send_local_broadcast(false);
+    }
+}
+
+// the cursor may be over a unit instead of the ground, aiming at it is fine too
+fn point_under_cursor(msg: &messages::CastAbility) -> Option<Vec3> {
+    physics::raycast_first(msg.ray_origin, msg.ray_dir).map(|hit| hit.position)
+}
@@ -0,0 +1,616 @@
+//! What lane creeps, camp monsters and towers do each frame, decided the same way by the creeps
+//! and towers modules and by the [`Simulation`](crate::simulation::Simulation).
+//!
+//! A caller gathers what is around its units once a frame in a [`Surroundings`], and answers
+//! through [`Senses`] what only it knows, like which units are still alive or which heroes are
+//! fighting. It then carries out the [`CreepAction`] or [`TowerAction`] it gets back with its
+//! own units: moving them, playing animations, hitting or shooting.
+
+use glam::Vec2;
+
+use crate::{
+    creeps::{TargetKind, Targeting},
+    map_data::{lane_position, BASE_RADIUS, TOWER_WIDTH},
+    rules,
+    spatial::SpatialGrid,
+    stats::{Stat, StatBlock},
+    steering::{Neighbour, Obstacle, Steering},
+};
+
+/// A creep walking its lane heads for the next path point once this close to the current
+/// one, and a creep walking back from a chase is back once this close to its leash origin.
+pub const PATH_POINT_REACHED_DISTANCE: f32 = 1.0;
+
+/// Side of the cells of the grids creeps look for enemies and allies in, a quarter of their
+/// sight range.
+pub const CREEP_GRID_CELL_SIZE: f32 = 2.5;
+
+/// Units per second below which a walking creep stands still, like at the end of its lane.
+pub const CREEP_IDLE_SPEED: f32 = 0.1;
+
+/// What a creep is doing. `T` is how its module names units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CreepState<T> {
+    /// Walking its lane, towards the point of the lane path at `next_point`.
+    Move {
+        next_point: usize,
+    },
+    Pursuit(T),
+    Attack(T),
+    /// Walking back to its leash origin after chasing something too far.
+    Return,
+    /// A monster waiting at its spot in its camp.
+    Camp,
+}
+
+/// A unit as the creeps around it see it at the start of a frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeenUnit<T> {
+    pub id: T,
+    pub team: u32,
+    pub position: Vec2,
+    pub radius: f32,
+    /// Alive, and for towers and bases, not protected by another tower.
+    pub targetable: bool,
+}
+
+/// Everything around the creeps in a frame, gathered once for all of them and sorted by where
+/// it is.
+#[derive(Debug, Clone)]
+pub struct Surroundings<T> {
+    steering: Steering,
+    targeting: Targeting,
+    heroes: SpatialGrid<SeenUnit<T>>,
+    creeps: SpatialGrid<SeenUnit<T>>,
+    towers: SpatialGrid<SeenUnit<T>>,
+    bases: SpatialGrid<SeenUnit<T>>,
+    // the biggest creep, allies further than it can't be close enough to push
+    widest_creep: f32,
+    // what creeps walk around, with the tower or base it is, `None` for the obstacles of the map
+    obstacles: Vec<(Option<T>, Obstacle)>,
+}
+
+impl<T: Copy + PartialEq> Surroundings<T> {
+    /// Nothing yet but the `obstacles` of the map, on a ground of `ground_size`.
+    pub fn new(
+        ground_size: f32,
+        steering: Steering,
+        targeting: Targeting,
+        obstacles: &[Obstacle],
+    ) -> Self {
+        let grid = || SpatialGrid::new(ground_size, CREEP_GRID_CELL_SIZE);
+        Self {
+            steering,
+            targeting,
+            heroes: grid(),
+            creeps: grid(),
+            towers: grid(),
+            bases: grid(),
+            widest_creep: 0.,
+            obstacles: obstacles.iter().map(|obstacle| (None, *obstacle)).collect(),
+        }
+    }
+
+    pub fn targeting(&self) -> &Targeting {
+        &self.targeting
+    }
+
+    pub fn add_hero(&mut self, hero: SeenUnit<T>) {
+        self.heroes.insert(hero.position, hero);
+    }
+
+    pub fn add_creep(&mut self, creep: SeenUnit<T>) {
+        self.widest_creep = self.widest_creep.max(creep.radius);
+        self.creeps.insert(creep.position, creep);
+    }
+
+    /// A tower, which creeps also walk around.
+    pub fn add_tower(&mut self, tower: SeenUnit<T>) {
+        let obstacle = Obstacle::Box {
+            center: tower.position,
+            size: Vec2::splat(TOWER_WIDTH),
+        };
+        self.obstacles.push((Some(tower.id), obstacle));
+        self.towers.insert(tower.position, tower);
+    }
+
+    /// A base, which creeps also walk around.
+    pub fn add_base(&mut self, base: SeenUnit<T>) {
+        let obstacle = Obstacle::Circle {
+            center: base.position,
+            radius: BASE_RADIUS,
+        };
+        self.obstacles.push((Some(base.id), obstacle));
+        self.bases.insert(base.position, base);
+    }
+
+    /// What a creep of `team` at `position` goes after on its own: the closest enemy within
+    /// `sight_range` of the first kind of the targeting priority that has one. Towers and bases
+    /// protected by other towers are left alone until those fall, and so are neutral monsters.
+    pub fn closest_enemy(&self, team: u32, position: Vec2, sight_range: f32) -> Option<T> {
+        rules::pick_target(&self.targeting.priority, |kind| {
+            let units = match kind {
+                TargetKind::Creeps => &self.creeps,
+                TargetKind::Heroes => &self.heroes,
+                TargetKind::Towers => &self.towers,
+                TargetKind::Bases => &self.bases,
+            };
+            units
+                .nearest(position, sight_range, |unit| {
+                    rules::is_lane_enemy(team, unit.team) && unit.targetable
+                })
+                .map(|(_, unit)| unit.id)
+        })
+    }
+
+    /// The closest creep a tower of `team` at `position` can shoot.
+    pub fn closest_enemy_creep(&self, team: u32, position: Vec2, range: f32) -> Option<T> {
+        self.creeps
+            .nearest(position, range, |unit| {
+                rules::is_lane_enemy(team, unit.team) && unit.targetable
+            })
+            .map(|(_, unit)| unit.id)
+    }
+
+    /// Units per second `creep` walks with towards `target`, steering around its allies and
+    /// the towers, bases and obstacles on the way, except `ignored`. So a wave flows around a
+    /// creep that bumped into something instead of stopping behind it.
+    pub fn velocity(&self, creep: &Creep<'_, T>, target: Vec2, ignored: Option<T>) -> Vec2 {
+        let reach = creep.radius + self.widest_creep + self.steering.separation_distance;
+        let allies: Vec<Neighbour> = self
+            .creeps
+            .within(creep.position, reach)
+            .map(|(_, other)| other)
+            .filter(|other| other.team == creep.team && other.id != creep.id)
+            .map(|other| Neighbour {
+                position: other.position,
+                radius: other.radius,
+            })
+            .collect();
+        let obstacles: Vec<Obstacle> = self
+            .obstacles
+            .iter()
+            .filter(|(id, _)| id.is_none() || *id != ignored)
+            .map(|(_, obstacle)| *obstacle)
+            .collect();
+
+        // slows from status effects are already in the stats
+        let speed = creep.stats[Stat::MoveSpeed];
+        let velocity = self.steering.velocity(
+            creep.position,
+            creep.radius,
+            speed,
+            target,
+            &allies,
+            &obstacles,
+        );
+        match velocity.length() < CREEP_IDLE_SPEED {
+            true => Vec2::ZERO,
+            false => velocity,
+        }
+    }
+}
+
+/// What a creep learns from its module rather than from the [`Surroundings`].
+pub trait Senses<T> {
+    /// What the creep goes after when it is free to: for lane creeps usually
+    /// [`Surroundings::closest_enemy`], for monsters whoever hit their camp.
+    fn find_target(&self) -> Option<T>;
+
+    /// An enemy hero hitting an allied hero nearby, which wins over whatever the creep was
+    /// fighting.
+    fn aggressor(&self) -> Option<T> {
+        None
+    }
+
+    /// Where `unit` stands, `None` once it is dead or gone.
+    fn position_of(&self, unit: T) -> Option<Vec2>;
+}
+
+/// A creep about to decide what it does, as it is at the start of the frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Creep<'a, T> {
+    pub id: T,
+    pub team: u32,
+    pub position: Vec2,
+    pub radius: f32,
+    pub stats: StatBlock,
+    /// Seconds left before it can attack again.
+    pub attack_cooldown: f32,
+    /// Where it left its lane to chase something, or its spot in the camp for monsters.
+    pub leash_origin: Option<Vec2>,
+    /// The path of its lane, `None` for monsters.
+    pub lane: Option<&'a [Vec2]>,
+}
+
+/// What a creep does this frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CreepAction<T> {
+    pub state: CreepState<T>,
+    pub leash_origin: Option<Vec2>,
+    /// Zero when it stands still.
+    pub velocity: Vec2,
+    pub attack_cooldown: f32,
+    /// What it hits, or shoots for ranged and siege creeps, this frame.
+    pub attack: Option<T>,
+}
+
+/// Decides what `creep` does during a frame of `delta_time` seconds: the state it goes to, and
+/// how it walks or attacks in that state.
+pub fn creep_action<T: Copy + PartialEq>(
+    creep: &Creep<'_, T>,
+    state: CreepState<T>,
+    surroundings: &Surroundings<T>,
+    senses: &impl Senses<T>,
+    delta_time: f32,
+) -> CreepAction<T> {
+    let state = next_state(creep, state, surroundings.targeting(), senses);
+
+    let mut leash_origin = creep.leash_origin;
+    match state {
+        // back on its lane, the next chase is measured from wherever it starts
+        CreepState::Move { .. } => leash_origin = None,
+        CreepState::Pursuit(_) => {
+            leash_origin.get_or_insert(creep.position);
+        }
+        _ => {}
+    }
+
+    let mut action = CreepAction {
+        state,
+        leash_origin,
+        velocity: Vec2::ZERO,
+        attack_cooldown: creep.attack_cooldown,
+        attack: None,
+    };
+    match state {
+        CreepState::Move { next_point } => {
+            let path = creep.lane.unwrap_or_default();
+            if let Some(point) = path.get(next_point) {
+                action.velocity = surroundings.velocity(creep, *point, None);
+            }
+        }
+        CreepState::Pursuit(target) => {
+            // the creep does not walk around what it is running after
+            if let Some(position) = senses.position_of(target) {
+                action.velocity = surroundings.velocity(creep, position, Some(target));
+            }
+        }
+        CreepState::Attack(target) => {
+            let interval = creep.stats[Stat::AttackInterval];
+            let (attacks, cooldown) =
+                rules::attack_step(creep.attack_cooldown, delta_time, interval);
+            action.attack_cooldown = cooldown;
+            if attacks {
+                action.attack = Some(target);
+            }
+        }
+        CreepState::Return => {
+            let origin = leash_origin.unwrap_or(creep.position);
+            action.velocity = surroundings.velocity(creep, origin, None);
+        }
+        CreepState::Camp => {}
+    }
+    action
+}
+
+/// The point of `path` a creep at `position` heads for when it gets back on its lane: the
+/// closest one ahead of it, as the one it was walking to may be behind it by now.
+pub fn lane_reentry(path: &[Vec2], position: Vec2) -> usize {
+    lane_position(path, position).map_or(0, |lane| lane.next_point)
+}
+
+fn next_state<T: Copy + PartialEq>(
+    creep: &Creep<'_, T>,
+    state: CreepState<T>,
+    targeting: &Targeting,
+    senses: &impl Senses<T>,
+) -> CreepState<T> {
+    let distance_to = |target: T| {
+        senses
+            .position_of(target)
+            .map(|position| position.distance(creep.position))
+    };
+    // once there is nothing left to fight, lane creeps go on along their lane and monsters
+    // walk back to their camp, where they wait
+    let after_fight = || match creep.lane {
+        Some(path) => CreepState::Move {
+            next_point: lane_reentry(path, creep.position),
+        },
+        None => CreepState::Return,
+    };
+    let home = || match creep.lane {
+        Some(_) => after_fight(),
+        None => CreepState::Camp,
+    };
+
+    match state {
+        CreepState::Move { next_point } => match (senses.find_target(), creep.lane) {
+            (Some(target), _) => CreepState::Pursuit(target),
+            (None, Some(path))
+                if path.get(next_point).is_some_and(|point| {
+                    point.distance(creep.position) < PATH_POINT_REACHED_DISTANCE
+                }) =>
+            {
+                // the last path point of the lane is where the creep stays
+                CreepState::Move {
+                    next_point: (next_point + 1).min(path.len() - 1),
+                }
+            }
+            (None, _) => state,
+        },
+        CreepState::Camp => match senses.find_target() {
+            Some(target) => CreepState::Pursuit(target),
+            None => state,
+        },
+        CreepState::Pursuit(target) => {
+            // a hero hitting an allied hero nearby wins over whatever the creep was chasing,
+            // and a target that died on the way is swapped for the next one around
+            let target = senses
+                .aggressor()
+                .or_else(|| distance_to(target).map(|_| target))
+                .or_else(|| senses.find_target());
+            let origin = creep.leash_origin.unwrap_or(creep.position);
+            let attack_range = creep.stats[Stat::AttackRange];
+            let sight_range = creep.stats[Stat::SightRange];
+            match target.and_then(|target| Some((target, distance_to(target)?))) {
+                None => after_fight(),
+                // chased too far from its lane or camp, it gives up and walks back
+                Some(_) if rules::is_leashed(origin, creep.position, targeting.leash_distance) => {
+                    CreepState::Return
+                }
+                Some((target, distance)) if distance < attack_range => CreepState::Attack(target),
+                Some((_, distance)) if distance > sight_range => after_fight(),
+                Some((target, _)) => CreepState::Pursuit(target),
+            }
+        }
+        CreepState::Attack(target) => {
+            if let Some(aggressor) = senses.aggressor().filter(|aggressor| *aggressor != target) {
+                return CreepState::Pursuit(aggressor);
+            }
+            match distance_to(target) {
+                Some(distance) if distance <= creep.stats[Stat::AttackRange] => state,
+                _ => match senses.find_target() {
+                    Some(target) => CreepState::Pursuit(target),
+                    None => after_fight(),
+                },
+            }
+        }
+        // it walks back without looking at enemies on the way, so it is not pulled away again
+        CreepState::Return => match creep.leash_origin {
+            Some(origin) if origin.distance(creep.position) >= PATH_POINT_REACHED_DISTANCE => state,
+            _ => home(),
+        },
+    }
+}
+
+/// What a tower does this frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TowerAction<T> {
+    /// `None` when nothing is in range.
+    pub target: Option<T>,
+    pub attack_cooldown: f32,
+    /// Whether it shoots its target this frame.
+    pub attacks: bool,
+}
+
+/// A frame of `delta_time` seconds of a tower with `attack_cooldown` seconds left: it keeps
+/// shooting `current` while `in_range` says it can, or else what `next_target` finds.
+pub fn tower_action<T: Copy>(
+    current: Option<T>,
+    in_range: impl Fn(T) -> bool,
+    next_target: impl FnOnce() -> Option<T>,
+    attack_cooldown: f32,
+    interval: f32,
+    delta_time: f32,
+) -> TowerAction<T> {
+    let target = current
+        .filter(|target| in_range(*target))
+        .or_else(next_target);
+    let (attacks, attack_cooldown) = match target {
+        Some(_) => rules::attack_step(attack_cooldown, delta_time, interval),
+        // nothing to shoot at, its cooldown waits for the next one
+        None => (false, attack_cooldown),
+    };
+    TowerAction {
+        target,
+        attack_cooldown,
+        attacks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const STEERING: Steering = Steering {
+        separation_distance: 0.5,
+        separation_weight: 1.,
+        avoidance_distance: 1.,
+        avoidance_weight: 1.,
+        arrival_distance: 0.5,
+    };
+    const PATH: [Vec2; 3] = [Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(20., 0.)];
+
+    // units are known by their index, and alive while they have a position
+    #[derive(Default)]
+    struct World {
+        positions: HashMap<u32, Vec2>,
+        target: Option<u32>,
+        aggressor: Option<u32>,
+    }
+
+    impl Senses<u32> for World {
+        fn find_target(&self) -> Option<u32> {
+            self.target
+        }
+
+        fn aggressor(&self) -> Option<u32> {
+            self.aggressor
+        }
+
+        fn position_of(&self, unit: u32) -> Option<Vec2> {
+            self.positions.get(&unit).copied()
+        }
+    }
+
+    fn surroundings() -> Surroundings<u32> {
+        let targeting = Targeting {
+            priority: vec![TargetKind::Creeps, TargetKind::Towers],
+            aggro_range: 5.,
+            leash_distance: 8.,
+        };
+        Surroundings::new(100., STEERING, targeting, &[])
+    }
+
+    fn creep(position: Vec2) -> Creep<'static, u32> {
+        let mut stats = StatBlock::ZERO;
+        stats[Stat::MoveSpeed] = 3.;
+        stats[Stat::SightRange] = 10.;
+        stats[Stat::AttackRange] = 1.5;
+        stats[Stat::AttackInterval] = 1.;
+        Creep {
+            id: 0,
+            team: 0,
+            position,
+            radius: 0.5,
+            stats,
+            attack_cooldown: 0.,
+            leash_origin: None,
+            lane: Some(&PATH),
+        }
+    }
+
+    fn seen(id: u32, team: u32, position: Vec2) -> SeenUnit<u32> {
+        SeenUnit {
+            id,
+            team,
+            position,
+            radius: 0.5,
+            targetable: true,
+        }
+    }
+
+    #[test]
+    fn lane_creep_walks_to_the_next_point_once_it_reached_one() {
+        let creep = creep(Vec2::new(9.5, 0.));
+        let state = CreepState::Move { next_point: 1 };
+        let action = creep_action(&creep, state, &surroundings(), &World::default(), 0.1);
+
+        assert_eq!(action.state, CreepState::Move { next_point: 2 });
+        assert!(action.velocity.x > 0.);
+        assert_eq!(action.attack, None);
+    }
+
+    #[test]
+    fn creep_chases_what_it_sees_and_attacks_it_in_range() {
+        let mut world = World {
+            target: Some(1),
+            ..World::default()
+        };
+        world.positions.insert(1, Vec2::new(5., 3.));
+        let creep = creep(Vec2::new(5., 0.));
+
+        let action = creep_action(
+            &creep,
+            CreepState::Move { next_point: 1 },
+            &surroundings(),
+            &world,
+            0.1,
+        );
+        assert_eq!(action.state, CreepState::Pursuit(1));
+        assert_eq!(action.leash_origin, Some(creep.position));
+        assert!(action.velocity.y > 0.);
+
+        world.positions.insert(1, Vec2::new(5., 1.));
+        let action = creep_action(&creep, CreepState::Pursuit(1), &surroundings(), &world, 0.1);
+        assert_eq!(action.state, CreepState::Attack(1));
+        assert_eq!(action.attack, Some(1));
+        assert_eq!(action.attack_cooldown, 1.);
+        assert_eq!(action.velocity, Vec2::ZERO);
+    }
+
+    #[test]
+    fn leashed_creep_walks_back_then_rejoins_its_lane() {
+        let mut world = World::default();
+        world.positions.insert(1, Vec2::new(14., 9.));
+        let mut creep = creep(Vec2::new(14., 8.5));
+        creep.leash_origin = Some(Vec2::new(14., 0.));
+
+        let action = creep_action(&creep, CreepState::Pursuit(1), &surroundings(), &world, 0.1);
+        assert_eq!(action.state, CreepState::Return);
+        assert!(action.velocity.y < 0.);
+
+        creep.position = Vec2::new(14., 0.5);
+        let action = creep_action(&creep, CreepState::Return, &surroundings(), &world, 0.1);
+        assert_eq!(action.state, CreepState::Move { next_point: 2 });
+        assert_eq!(action.leash_origin, None);
+    }
+
+    #[test]
+    fn monster_goes_back_to_its_camp_after_a_fight() {
+        let mut creep = creep(Vec2::new(3., 0.));
+        creep.lane = None;
+        creep.leash_origin = Some(Vec2::ZERO);
+        let world = World::default();
+
+        let action = creep_action(&creep, CreepState::Attack(1), &surroundings(), &world, 0.1);
+        assert_eq!(action.state, CreepState::Return);
+
+        creep.position = Vec2::new(0.5, 0.);
+        let action = creep_action(&creep, CreepState::Return, &surroundings(), &world, 0.1);
+        assert_eq!(action.state, CreepState::Camp);
+        assert_eq!(action.velocity, Vec2::ZERO);
+    }
+
+    #[test]
+    fn aggressor_pulls_an_attacking_creep_away() {
+        let mut world = World {
+            aggressor: Some(2),
+            ..World::default()
+        };
+        world.positions.insert(1, Vec2::new(1., 0.));
+        world.positions.insert(2, Vec2::new(4., 0.));
+        let creep = creep(Vec2::ZERO);
+
+        let action = creep_action(&creep, CreepState::Attack(1), &surroundings(), &world, 0.1);
+        assert_eq!(action.state, CreepState::Pursuit(2));
+        assert_eq!(action.attack, None);
+    }
+
+    #[test]
+    fn closest_enemy_follows_the_priority_and_skips_protected_units() {
+        let mut surroundings = surroundings();
+        surroundings.add_creep(seen(1, 0, Vec2::new(1., 0.)));
+        surroundings.add_creep(seen(2, 2, Vec2::new(1., 1.)));
+        surroundings.add_tower(seen(3, 1, Vec2::new(2., 0.)));
+        assert_eq!(surroundings.closest_enemy(0, Vec2::ZERO, 10.), Some(3));
+
+        surroundings.add_creep(seen(4, 1, Vec2::new(6., 0.)));
+        assert_eq!(surroundings.closest_enemy(0, Vec2::ZERO, 10.), Some(4));
+
+        let mut surroundings = self::surroundings();
+        let mut protected = seen(5, 1, Vec2::new(1., 0.));
+        protected.targetable = false;
+        surroundings.add_tower(protected);
+        assert_eq!(surroundings.closest_enemy(0, Vec2::ZERO, 10.), None);
+    }
+
+    #[test]
+    fn tower_keeps_its_target_while_in_range() {
+        let action = tower_action(Some(1), |_| true, || Some(2), 0., 1., 0.1);
+        assert_eq!(action.target, Some(1));
+        assert!(action.attacks);
+        assert_eq!(action.attack_cooldown, 1.);
+
+        let action = tower_action(Some(1), |unit| unit != 1, || Some(2), 0.5, 1., 0.1);
+        assert_eq!(action.target, Some(2));
+        assert!(!action.attacks);
+        assert_eq!(action.attack_cooldown, 0.5 - 0.1);
+
+        let action = tower_action(None::<u32>, |_| true, || None, 0.5, 1., 0.1);
+        assert_eq!(action.target, None);
+        assert_eq!(action.attack_cooldown, 0.5);
+    }
+}
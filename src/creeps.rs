@@ -0,0 +1,256 @@
+//! Creep kinds and the wave schedule, loaded from `data/creeps.toml`.
+
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::{
+    stats::{Stat, StatBlock},
+    steering::Steering,
+};
+
+const CREEPS: &str = include_str!("../data/creeps.toml");
+
+/// Seconds between the start of a match and its first wave.
+pub const TIME_TO_FIRST_WAVE: f32 = 5.;
+
+/// Stored in the `creep_kind` component as its `u32` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CreepKind {
+    Melee = 0,
+    Ranged = 1,
+    Siege = 2,
+}
+
+impl CreepKind {
+    pub const ALL: [CreepKind; 3] = [CreepKind::Melee, CreepKind::Ranged, CreepKind::Siege];
+
+    pub fn from_u32(kind: u32) -> Option<Self> {
+        Self::ALL.get(kind as usize).copied()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreepRoster {
+    pub waves: WaveSchedule,
+    /// How creeps walk around each other and around bases, towers and obstacles.
+    pub steering: Steering,
+    /// What creeps go after, and how far.
+    pub targeting: Targeting,
+    pub melee: CreepDefinition,
+    pub ranged: CreepDefinition,
+    pub siege: CreepDefinition,
+}
+
+/// What a creep can go after.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetKind {
+    Creeps,
+    Heroes,
+    Towers,
+    Bases,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Targeting {
+    /// Kinds of enemies in sight a creep picks from, first to last. It takes the closest
+    /// enemy of the first kind that has one.
+    pub priority: Vec<TargetKind>,
+    /// An enemy hero attacking an allied hero this close to a creep becomes its target,
+    /// whatever it was doing.
+    pub aggro_range: f32,
+    /// How far from where it left its lane a creep chases something before walking back.
+    pub leash_distance: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WaveSchedule {
+    /// Seconds between two waves.
+    pub interval: f32,
+    /// Creeps of each kind in every wave, per lane.
+    pub melee: usize,
+    pub ranged: usize,
+    /// Siege creeps join every `siege_every` waves.
+    pub siege: usize,
+    pub siege_every: u32,
+}
+
+/// When the next wave leaves. The creeps module keeps it in the `spawn_timer` and
+/// `wave_number` resources.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WaveTimer {
+    /// Seconds left before the next wave.
+    pub time_to_next_wave: f32,
+    /// Waves sent since the match started.
+    pub wave_number: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreepDefinition {
+    pub name: String,
+    /// Prefab url of the model of each team, indexed by team. Kinds camps use also need one
+    /// for the neutral team.
+    pub models: Vec<String>,
+    pub animations: CreepAnimations,
+    /// Units per second.
+    pub speed: f32,
+    pub health: i32,
+    pub attack_range: f32,
+    pub attack_damage: i32,
+    /// Seconds between two attacks.
+    pub attack_cooldown: f32,
+    /// How far the creep looks for enemies to chase.
+    pub sight_range: f32,
+    /// Radius of the creep's character controller.
+    pub radius: f32,
+    /// Gold earned by the player who lands the killing blow.
+    pub bounty: u32,
+    /// Experience shared by the enemy heroes around when the creep dies.
+    pub experience: u32,
+    /// Units per second of the projectile the creep shoots. Creeps without one hit in melee.
+    #[serde(default)]
+    pub projectile_speed: Option<f32>,
+}
+
+/// Animation clip urls of a creep kind.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreepAnimations {
+    pub idle: String,
+    pub walk: String,
+    pub run: String,
+    pub attack: String,
+    pub death: String,
+}
+
+#[derive(Debug)]
+pub enum CreepRosterError {
+    Toml(toml::de::Error),
+    MissingModel { kind: CreepKind, team: u32 },
+    NoSiegeWave,
+    NoTargetPriority,
+    DuplicateTargetKind(TargetKind),
+}
+
+impl fmt::Display for CreepRosterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreepRosterError::Toml(error) => write!(f, "invalid creep roster: {error}"),
+            CreepRosterError::MissingModel { kind, team } => {
+                write!(f, "{kind:?} creeps have no model for team {team}")
+            }
+            CreepRosterError::NoSiegeWave => write!(f, "waves.siege_every must be at least 1"),
+            CreepRosterError::NoTargetPriority => {
+                write!(
+                    f,
+                    "targeting.priority must list at least one kind of target"
+                )
+            }
+            CreepRosterError::DuplicateTargetKind(kind) => {
+                write!(f, "{kind:?} is listed twice in targeting.priority")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CreepRosterError {}
+
+impl CreepRoster {
+    /// The creeps shipped in `data/creeps.toml`. Panics if the file is broken.
+    pub fn load() -> Self {
+        match Self::parse(CREEPS) {
+            Ok(roster) => roster,
+            Err(error) => panic!("data/creeps.toml: {error}"),
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self, CreepRosterError> {
+        let roster: CreepRoster = toml::from_str(source).map_err(CreepRosterError::Toml)?;
+        roster.validate()?;
+        Ok(roster)
+    }
+
+    pub fn kind(&self, kind: CreepKind) -> &CreepDefinition {
+        match kind {
+            CreepKind::Melee => &self.melee,
+            CreepKind::Ranged => &self.ranged,
+            CreepKind::Siege => &self.siege,
+        }
+    }
+
+    fn validate(&self) -> Result<(), CreepRosterError> {
+        if self.waves.siege_every == 0 {
+            return Err(CreepRosterError::NoSiegeWave);
+        }
+        let priority = &self.targeting.priority;
+        if priority.is_empty() {
+            return Err(CreepRosterError::NoTargetPriority);
+        }
+        for (index, kind) in priority.iter().enumerate() {
+            if priority[..index].contains(kind) {
+                return Err(CreepRosterError::DuplicateTargetKind(*kind));
+            }
+        }
+        // Mars and Jupiter both need a model for every kind
+        for kind in CreepKind::ALL {
+            for team in 0..2 {
+                if self.kind(kind).models.get(team as usize).is_none() {
+                    return Err(CreepRosterError::MissingModel { kind, team });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CreepDefinition {
+    pub fn base_stats(&self) -> StatBlock {
+        let mut stats = StatBlock::ZERO;
+        stats[Stat::MoveSpeed] = self.speed;
+        stats[Stat::AttackDamage] = self.attack_damage as f32;
+        stats[Stat::AttackRange] = self.attack_range;
+        stats[Stat::AttackInterval] = self.attack_cooldown;
+        stats[Stat::SightRange] = self.sight_range;
+        stats
+    }
+}
+
+impl WaveSchedule {
+    /// The creeps of the `wave`-th wave (starting at 0) of a lane, front row first:
+    /// melee creeps lead, ranged creeps follow and siege creeps close the march.
+    pub fn composition(&self, wave: u32) -> Vec<CreepKind> {
+        let siege = match (wave + 1) % self.siege_every {
+            0 => self.siege,
+            _ => 0,
+        };
+        let mut wave = vec![CreepKind::Melee; self.melee];
+        wave.extend(vec![CreepKind::Ranged; self.ranged]);
+        wave.extend(vec![CreepKind::Siege; siege]);
+        wave
+    }
+}
+
+impl Default for WaveTimer {
+    fn default() -> Self {
+        Self {
+            time_to_next_wave: TIME_TO_FIRST_WAVE,
+            wave_number: 0,
+        }
+    }
+}
+
+impl WaveTimer {
+    /// Runs a frame of `delta_time` seconds. Returns the number of the wave that leaves now,
+    /// if one does.
+    pub fn tick(&mut self, delta_time: f32, schedule: &WaveSchedule) -> Option<u32> {
+        if self.time_to_next_wave > 0. {
+            self.time_to_next_wave -= delta_time;
+            return None;
+        }
+        let wave = self.wave_number;
+        self.wave_number += 1;
+        self.time_to_next_wave = schedule.interval;
+        Some(wave)
+    }
+}
@@ -15,462 +15,809 @@ use ambient_api::{
     physics::move_character, 
     prelude::{
         Quat, Entity, EntityId, Vec3, Vec2, Vec3Swizzles,
-        vec3, delta_time, 
+        vec3, delta_time, run_async, sleep, ModuleMessage,
     }, main, 
 };
-use components::{team, is_creep, creep_current_state, creep_next_state, pursuit_target, attack_target};
+use amoba::{
+    behaviour::{self, CreepState, SeenUnit, Senses},
+    creeps::{CreepAnimations, CreepDefinition, CreepKind, CreepRoster, Targeting, WaveTimer},
+    map_data::{lane_position, CampDefinition, FormationDefinition, LanePosition, MapData, BASE_RADIUS, NEUTRAL_TEAM, TOWER_WIDTH},
+    match_state::MatchState,
+    state_machine::{State, StateId, StateMachine},
+    stats::{Stat, StatBlock},
+    steering::{Obstacle, Steering},
+};
+use components::{team, is_creep, creep_current_state, pursuit_target, attack_target};
+use std::{collections::HashMap, rc::Rc};
+
+#[path = "../server_common.rs"]
+mod server_common;
+use server_common::{deal_damage, is_alive, is_targetable, stat};
 
 const INIT_POS: f32 = std::f32::consts::FRAC_PI_2;
 
 const MARS_TEAM: u32 = 0;
 const JUPYTER_TEAM: u32 = 1;
 
-const TIME_TO_NEXT_CREEP_SPAWNS: f32 = 5.;
-
-const CREEP_MOVE_STATE: u16 = 0;
-const CREEP_PURSUIT_STATE: u16 = 1;
-const CREEP_ATTACK_STATE: u16 = 2;
-
-const CREEP_MAXIMUM_PURSUIT_CHECK_DISTANCE: f32 = 10.;
-const CREEP_MAXIMUM_ATTACK_CHECK_DISTANCE: f32 = 5.;
+//Kept in the creep_current_state component. Other states can be added to the machine built in creep_states, with ids of their own
+const CREEP_MOVE_STATE: StateId = StateId(0);
+const CREEP_PURSUIT_STATE: StateId = StateId(1);
+const CREEP_ATTACK_STATE: StateId = StateId(2);
+const CREEP_STUNNED_STATE: StateId = StateId(3);
+const CREEP_DEAD_STATE: StateId = StateId(4);
+const CREEP_RETURN_STATE: StateId = StateId(5);
+const CREEP_CAMP_STATE: StateId = StateId(6);
+
+//Units per second creeps are pushed down with, so they stay on the ground
+const FALL_SPEED: f32 = 6.;
+
+const CREEP_DEATH_ANIMATION_DURATION: f32 = 2.5;
+
+const CREEP_PROJECTILE_LAUNCH_HEIGHT: f32 = 1.5;
+
+macro_rules! idle_animation_state { () => { vec![1.0, 0.0, 0.0, 0.0, 0.0] }; }
+macro_rules! walk_animation_state { () => { vec![0.0, 1.0, 0.0, 0.0, 0.0] }; }
+macro_rules! pursuit_animation_state { () => { vec![0.0, 0.0, 1.0, 0.0, 0.0] }; }
+macro_rules! attack_animation_state { () => { vec![0.0, 0.0, 0.0, 1.0, 0.0] }; }
+macro_rules! death_animation_state { () => { vec![0.0, 0.0, 0.0, 0.0, 1.0] }; }
+
+//Animation players of a creep kind, indexed by the creep_kind component
+#[derive(Clone, Copy)]
+struct CreepAnimationPlayers {
+    idle: AnimationPlayer,
+    walk: AnimationPlayer,
+    pursuit: AnimationPlayer,
+    attack: AnimationPlayer,
+    death: AnimationPlayer,
+}
 
-const CREEP_MOVE_STATE_SPEED: f32 = 0.05;
+impl CreepAnimationPlayers {
+    fn load(animations: &CreepAnimations) -> Self {
+        let clip = |url: &str| PlayClipFromUrlNode::new(asset::url(url).unwrap());
+        Self {
+            idle: AnimationPlayer::new(&clip(&animations.idle)),
+            walk: AnimationPlayer::new(&clip(&animations.walk)),
+            pursuit: AnimationPlayer::new(&clip(&animations.run)),
+            attack: AnimationPlayer::new(&clip(&animations.attack)),
+            death: AnimationPlayer::new(&clip(&animations.death)),
+        }
+    }
+}
 
-macro_rules! idle_animation_state { () => { vec![1.0, 0.0, 0.0, 0.0] }; }
-macro_rules! walk_animation_state { () => { vec![0.0, 1.0, 0.0, 0.0] }; }
-macro_rules! pursuit_animation_state { () => { vec![0.0, 0.0, 1.0, 0.0] }; }
-macro_rules! attack_animation_state { () => { vec![0.0, 0.0, 0.0, 1.0] }; }
+type CreepAnimationsByKind = [CreepAnimationPlayers; 3];
 
 #[main]
 pub fn main() {
-    let ranged_idle = PlayClipFromUrlNode::new(
-        asset::url("assets/anim/Zombie Idle.fbx/animations/mixamo.com.anim").unwrap(),
-    );
+    let creep_roster = CreepRoster::load();
+    let creep_animations: CreepAnimationsByKind = CreepKind::ALL.map(|kind| CreepAnimationPlayers::load(&creep_roster.kind(kind).animations));
 
-    let ranged_walk = PlayClipFromUrlNode::new(
-        asset::url("assets/anim/Zombie Walk.fbx/animations/mixamo.com.anim").unwrap(),
-    );
+    let wave_timer = WaveTimer::default();
+    entity::add_component(resources(), components::spawn_timer(), wave_timer.time_to_next_wave);
+    entity::add_component(resources(), components::wave_number(), wave_timer.wave_number);
+    
+    let map = MapData::load();
 
-    let ranged_pursuit = PlayClipFromUrlNode::new(
-        asset::url("assets/anim/Zombie Run.fbx/animations/mixamo.com.anim").unwrap(),
-    );
+    //Camps come out as soon as the match starts
+    entity::add_component(resources(), components::camp_respawn_timers(), vec![0.; map.camps.len()]);
 
-    let idle_player = AnimationPlayer::new(&ranged_idle);
-    let walk_player = AnimationPlayer::new(&ranged_walk);
-    let pursuit_player = AnimationPlayer::new(&ranged_pursuit);
-    
+    for camp in &map.camps {
+        for kind in &camp.monsters {
+            if creep_roster.kind(*kind).models.get(NEUTRAL_TEAM as usize).is_none() {
+                panic!("data/creeps.toml: {:?} creeps need a model for the neutral team, camp {:?} has some", kind, camp.name);
+            }
+        }
+    }
 
-    entity::add_component(resources(), components::spawn_timer(), TIME_TO_NEXT_CREEP_SPAWNS);
-    
-    checks_if_creeps_should_change_their_states_system();
+    creep_state_system(creep_states(), creep_roster.steering, creep_roster.targeting.clone(), &map, creep_animations);
 
-    creep_move_state_system(idle_player, walk_player);
-    creep_pursuit_state_system(idle_player, pursuit_player);
-    
-    creep_attack_state_system();
+    respawns_camps_system(creep_roster.clone(), creep_animations, map.camps.clone());
 
-    spawns_creeps_regularly_system(idle_player);
+    spawns_creeps_regularly_system(creep_roster, creep_animations, map.formation);
+
+    clears_creeps_on_match_reset();
         
 }
 
-fn checks_if_creeps_should_change_their_states_system() {
-
-    query((is_creep(), creep_current_state(), creep_next_state())).each_frame({
-        move |list| {
-            for (creep, (_, current_state, next_state)) in list {
-                if current_state != next_state {
-                    
-                    //Leaving current_state
-                    match current_state {
-                        CREEP_MOVE_STATE => {entity::remove_component(creep, components::target_pos());},
-                        CREEP_PURSUIT_STATE => {entity::remove_component(creep, pursuit_target());},
-                        CREEP_ATTACK_STATE => {entity::remove_component(creep, attack_target());},
-                        3_u16..=u16::MAX => panic!("How we reached this point where we want to leave a state that does not exist?"),
-                    }
+//What a creep state knows about its creep, gathered again every frame
+struct Creep {
+    model: EntityId,
+    team: u32,
+    position: Vec3,
+    radius: f32,
+    anim_model: EntityId,
+    animations: CreepAnimationPlayers,
+    surroundings: Rc<Surroundings>,
+    //The camp of data/map.toml a neutral monster belongs to, None for lane creeps
+    camp: Option<u32>,
+    //Picked by a state for the next one, like the enemy the move state found to chase
+    target: Option<EntityId>,
+}
 
-                    //Entering next_state
-                    match next_state {
-                        CREEP_MOVE_STATE => {
-                            let next_path_point = entity::get_component(creep, components::next_path_point()).unwrap();
-                            let target = get_component(next_path_point, translation()).unwrap();
-                            entity::add_component(creep, components::target_pos(), Vec2{x:target.x, y:target.y});
-                        },
-                        CREEP_PURSUIT_STATE => { /*TECHNOLOGICAL DEBT: by now, the pursuit_target() component is added by the move state itself in case of a state change*/ },
-                        CREEP_ATTACK_STATE => { /*TECHNOLOGICAL DEBT: by now, the attack_target() component is added by the pursuit state itself in case of a state change*/ },
-                        3_u16..=u16::MAX => panic!("How we reached this point where we want to go to a state that does not exist?"),
-                    }
+//Everything around the creeps this frame, gathered once for all of them
+struct Surroundings {
+    //The heroes, creeps, towers and bases sorted by where they are, and what creeps walk around
+    units: behaviour::Surroundings<EntityId>,
+    lanes: HashMap<u32, LanePath>,
+    //(attacker, its team, the victim's team, where the victim stands) of every hero hitting another hero
+    hero_fights: Vec<(EntityId, u32, u32, Vec2)>,
+    //Who last hit a monster of each camp, the whole camp fights back
+    camp_attackers: HashMap<u32, Vec<EntityId>>,
+}
 
-                    entity::set_component(creep, creep_current_state(), next_state);
-                    //println!("Changed state from {:?} to {:?}", current_state, next_state);
-                }
-            }
-        }
-    });
+//The path points of a lane in walking order, and where they are
+struct LanePath {
+    points: Vec<EntityId>,
+    path: Vec<Vec2>,
+}
 
+//What the behaviour shared with the simulation can't see in the surroundings
+impl Senses<EntityId> for Creep {
+    fn find_target(&self) -> Option<EntityId> {
+        find_target(self)
+    }
 
+    fn aggressor(&self) -> Option<EntityId> {
+        aggressor_nearby(self)
+    }
 
+    fn position_of(&self, unit: EntityId) -> Option<Vec2> {
+        match is_alive(unit) {
+            true => entity::get_component(unit, translation()).map(|position| position.xy()),
+            false => None,
+        }
+    }
 }
 
-fn creep_move_state_system(idle_player: AnimationPlayer, walk_player: AnimationPlayer){
-    let all_heroes_query = query((components::hero_model(), components::role(), components::hero_model())).build();
-    let all_bases_query = query(components::base_side()).build();
+//Builds the states every creep goes through. Guards are checked in the order they are added, so dying wins over being stunned
+fn creep_states() -> StateMachine<Creep> {
+    let mut machine = StateMachine::new();
+    machine.add_state(CREEP_MOVE_STATE, MoveState);
+    machine.add_state(CREEP_PURSUIT_STATE, PursuitState);
+    machine.add_state(CREEP_ATTACK_STATE, AttackState);
+    machine.add_state(CREEP_STUNNED_STATE, StunnedState);
+    machine.add_state(CREEP_DEAD_STATE, DeadState);
+    machine.add_state(CREEP_RETURN_STATE, ReturnState);
+    machine.add_state(CREEP_CAMP_STATE, CampState);
+
+    machine.from_any_when(CREEP_DEAD_STATE, |creep: &Creep| entity::get_component(creep.model, components::health()).map_or(false, |health| health <= 0));
+    //Stunned creeps don't move, attack or chase anything until the stun wears off
+    machine.from_any_when(CREEP_STUNNED_STATE, |creep: &Creep| entity::has_component(creep.model, components::is_stunned()));
+    machine.when(CREEP_STUNNED_STATE, CREEP_MOVE_STATE, |creep: &Creep| creep.camp.is_none() && !entity::has_component(creep.model, components::is_stunned()));
+    machine.when(CREEP_STUNNED_STATE, CREEP_RETURN_STATE, |creep: &Creep| creep.camp.is_some() && !entity::has_component(creep.model, components::is_stunned()));
+
+    machine.allow(CREEP_MOVE_STATE, CREEP_PURSUIT_STATE, |creep: &Creep| creep.target.map_or(false, is_alive));
+    machine.allow(CREEP_PURSUIT_STATE, CREEP_ATTACK_STATE, |creep: &Creep| creep.target.map_or(false, is_alive));
+    machine.allow(CREEP_PURSUIT_STATE, CREEP_MOVE_STATE, |_| true);
+    machine.allow(CREEP_PURSUIT_STATE, CREEP_RETURN_STATE, |_| true);
+    //A creep that lost its target or got pulled by a hero fight goes after the next one right away
+    machine.allow(CREEP_ATTACK_STATE, CREEP_PURSUIT_STATE, |creep: &Creep| creep.target.map_or(false, is_alive));
+    machine.allow(CREEP_ATTACK_STATE, CREEP_MOVE_STATE, |_| true);
+    machine.allow(CREEP_ATTACK_STATE, CREEP_RETURN_STATE, |_| true);
+    machine.allow(CREEP_RETURN_STATE, CREEP_MOVE_STATE, |_| true);
+
+    //Neutral monsters wait in their camp instead of walking a lane, and go back there after a fight
+    machine.allow(CREEP_CAMP_STATE, CREEP_PURSUIT_STATE, |creep: &Creep| creep.target.map_or(false, is_alive));
+    machine.allow(CREEP_RETURN_STATE, CREEP_CAMP_STATE, |_| true);
+    machine
+}
 
-    query(components::is_creep()).excludes(components::pursuit_target()).each_frame({
-        move |list| {
+fn creep_state_system(machine: StateMachine<Creep>, steering: Steering, targeting: Targeting, map: &MapData, creep_animations: CreepAnimationsByKind) {
+    let all_heroes_query = query((components::hero_model(), team())).build();
+    let hero_attacks_query = query((components::hero_model(), team(), components::hero_attack_target())).build();
+    let all_creeps_query = query((is_creep(), team(), translation())).excludes(components::is_dead()).build();
+    let all_towers_query = query((components::is_tower(), team(), translation())).build();
+    let all_bases_query = query((components::base_side(), translation())).build();
+    let spawn_points_query = query((components::is_creep_spawn_point(), components::lane())).build();
+    let camp_attackers_query = query((components::camp(), components::last_attacker())).build();
 
-            let all_heroes = all_heroes_query.evaluate();
+    let ground_size = map.ground_size;
 
-            for (creep_model, _) in list.iter() {             
-                let creep_team = entity::get_component(*creep_model, team()).unwrap();
+    let map_obstacles: Vec<Obstacle> = map.obstacles.iter().map(|obstacle| {
+        Obstacle::Box { center: obstacle.position, size: obstacle.size }
+    }).collect();
 
-                let creep_position = entity::get_component(*creep_model, translation()).unwrap();
+    query((is_creep(), creep_current_state())).excludes(components::is_dead()).each_frame({
+        move |list| {
+            let mut units = behaviour::Surroundings::new(ground_size, steering, targeting.clone(), &map_obstacles);
+
+            let heroes: Vec<SeenUnit<EntityId>> = all_heroes_query.evaluate().into_iter().filter_map(|(_, (hero_model, hero_team))| {
+                let position = entity::get_component(hero_model, translation())?;
+                Some(SeenUnit { id: hero_model, team: hero_team, position: position.xy(), radius: 0., targetable: is_alive(hero_model) })
+            }).collect();
+            for hero in &heroes {
+                units.add_hero(*hero);
+            }
 
-                let mut closest_hero: Option<EntityId> = None;
-                let mut distance_of_closest_hero: Option<f32> = None;
+            for (creep_model, (_, creep_team, position)) in all_creeps_query.evaluate() {
+                let radius = entity::get_component(creep_model, character_controller_radius()).unwrap_or(0.);
+                units.add_creep(SeenUnit { id: creep_model, team: creep_team, position: position.xy(), radius, targetable: is_alive(creep_model) });
+            }
 
-                for (hero_id, (_, hero_role, hero_model)) in &all_heroes {
-                    if creep_team%2 != hero_role%2 {
-                        let current_hero_position = entity::get_component(*hero_model, translation()).unwrap();
+            for (tower_id, (_, tower_team, position)) in all_towers_query.evaluate() {
+                units.add_tower(SeenUnit { id: tower_id, team: tower_team, position: position.xy(), radius: TOWER_WIDTH / 2., targetable: is_targetable(tower_id) });
+            }
 
-                        let distance_of_current_hero = (creep_position.xy() - current_hero_position.xy()).length();
+            for (base_id, (base_side, position)) in all_bases_query.evaluate() {
+                units.add_base(SeenUnit { id: base_id, team: base_side, position: position.xy(), radius: BASE_RADIUS, targetable: is_targetable(base_id) });
+            }
 
-                        match closest_hero {
-                            None => {
-                                if distance_of_current_hero <= CREEP_MAXIMUM_PURSUIT_CHECK_DISTANCE {
-                                    closest_hero = Some(*hero_model);
-                                    distance_of_closest_hero = Some(distance_of_current_hero);
-                                }
-                            }
-                            Some(_) => {
-                                if distance_of_current_hero < distance_of_closest_hero.unwrap() {
-                                    closest_hero = Some(*hero_id);
-                                    distance_of_closest_hero = Some(distance_of_current_hero);
-                                }
-                            }
-                        }
-                    }
-                }
-                
-                if closest_hero != None {
-                    //TECHNOLOGICAL DEBT: Is there a better way to do this?
-                    entity::add_component(*creep_model, pursuit_target(), closest_hero.unwrap());
-                    entity::set_component(*creep_model, creep_next_state(), CREEP_PURSUIT_STATE);
-                    continue;
+            let lanes = spawn_points_query.evaluate().into_iter().map(|(spawn_point, (_, lane))| {
+                let mut lane_path = LanePath { points: Vec::new(), path: Vec::new() };
+                let mut path_point = Some(spawn_point);
+                while let Some(point) = path_point {
+                    lane_path.points.push(point);
+                    lane_path.path.push(get_component(point, translation()).unwrap().xy());
+                    path_point = get_component(point, components::next_path_point());
                 }
+                (lane, lane_path)
+            }).collect();
 
-                let mut closest_enemy_creep: Option<EntityId> = None;
-                let mut distance_of_closest_enemy_creep: Option<f32> = None;
-
-                let team_of_first_creep = entity::get_component(*creep_model, team());
-
-                for (creep_model_2, _) in list.iter(){
-                    
-                    let team_of_second_creep = entity::get_component(*creep_model_2, team());
-
-                    if team_of_first_creep != team_of_second_creep {
-                        let position_of_second_creep = entity::get_component(*creep_model_2, translation()).unwrap();
-
-                        let distance_of_second_creep = (creep_position.xy() - position_of_second_creep.xy()).length();
-
-                        match closest_enemy_creep {
-                            None => {
-                                if distance_of_second_creep <= CREEP_MAXIMUM_PURSUIT_CHECK_DISTANCE {
-                                    closest_enemy_creep = Some(*creep_model_2);
-                                    distance_of_closest_enemy_creep = Some(distance_of_second_creep);
-                                }
-                            }
-                            Some(_) => {
-                                if distance_of_second_creep < distance_of_closest_enemy_creep.unwrap() {
-                                    closest_enemy_creep = Some(*creep_model_2);
-                                    distance_of_closest_enemy_creep = Some(distance_of_second_creep);
-                                }
-                                
-                            }
-                        }
-                    }
-                }
+            let hero_fights = hero_attacks_query.evaluate().into_iter().filter_map(|(_, (attacker, attacker_team, victim))| {
+                let victim = heroes.iter().find(|hero| hero.id == victim && hero.team != attacker_team)?;
+                Some((attacker, attacker_team, victim.team, victim.position))
+            }).collect();
 
-                if closest_enemy_creep != None {
-                    entity::add_component(*creep_model, pursuit_target(), closest_enemy_creep.unwrap());
-                    entity::set_component(*creep_model, creep_next_state(), CREEP_PURSUIT_STATE);
-                    continue;
-                }
+            let mut camp_attackers: HashMap<u32, Vec<EntityId>> = HashMap::new();
+            for (_, (camp, attacker)) in camp_attackers_query.evaluate() {
+                camp_attackers.entry(camp).or_default().push(attacker);
+            }
 
-                let mut closest_base: Option<EntityId> = None;
-                let mut distance_of_closest_enemy_base = None;
-
-                let all_bases = all_bases_query.evaluate();
-                for (base_id, base_side) in all_bases.iter(){
-                    if *base_side != team_of_first_creep.unwrap() {
-                        let position_of_base = entity::get_component(*base_id, translation()).unwrap();
-
-                        let distance_between_creep_and_base = (creep_position.xy() - position_of_base.xy()).length();
-
-                        match closest_base {
-                            None => {
-                                if distance_between_creep_and_base <= CREEP_MAXIMUM_PURSUIT_CHECK_DISTANCE {
-                                    closest_base = Some(*base_id);
-                                    distance_of_closest_enemy_base = Some(distance_between_creep_and_base);
-                                }
-                            }
-                            Some(_) => {
-                                if distance_between_creep_and_base <= distance_of_closest_enemy_base.unwrap() {
-                                    closest_base = Some(*base_id);
-                                    distance_of_closest_enemy_base = Some(distance_between_creep_and_base);
-                                }
-                            }
-                        }
+            let surroundings = Rc::new(Surroundings {
+                units,
+                lanes,
+                hero_fights,
+                camp_attackers,
+            });
+
+            for (creep_model, (_, current_state)) in list {
+                let mut creep = Creep {
+                    model: creep_model,
+                    team: entity::get_component(creep_model, team()).unwrap(),
+                    position: entity::get_component(creep_model, translation()).unwrap(),
+                    radius: entity::get_component(creep_model, character_controller_radius()).unwrap_or(0.),
+                    anim_model: entity::get_component(creep_model, components::anim_model()).unwrap(),
+                    animations: animations_of(creep_model, &creep_animations),
+                    surroundings: surroundings.clone(),
+                    camp: entity::get_component(creep_model, components::camp()),
+                    target: None,
+                };
+
+                //How far along its lane the creep got, to follow how waves push
+                if let Some(lane_position) = position_on_lane(&creep) {
+                    if entity::get_component(creep_model, components::lane_progress()) != Some(lane_position.progress) {
+                        entity::add_component(creep_model, components::lane_progress(), lane_position.progress);
                     }
                 }
 
-                if closest_base != None {
-                    entity::add_component(*creep_model, pursuit_target(), closest_base.unwrap());
-                    entity::set_component(*creep_model, creep_next_state(), CREEP_PURSUIT_STATE);
-                    continue;
+                let next_state = machine.step(StateId(current_state), &mut creep);
+                if next_state.0 != current_state {
+                    entity::set_component(creep_model, creep_current_state(), next_state.0);
+                    //println!("Changed state from {:?} to {:?}", machine.name(StateId(current_state)), machine.name(next_state));
                 }
             }
         }
     });
+}
 
-    query((components::is_creep(), components::target_pos())).each_frame({
-        move |list| {
-            for (model, (_, _)) in list {
-                
-                let anim_model = entity::get_component(model, components::anim_model()).unwrap();
+//Walks the lane from path point to path point, until an enemy comes in sight
+struct MoveState;
 
-                let anim_state = entity::get_component(anim_model, components::anim_state()).unwrap();
+impl State<Creep> for MoveState {
+    fn name(&self) -> &'static str { "move" }
 
-                if anim_state == attack_animation_state!() {
-                    continue;
-                }
+    fn enter(&self, creep: &mut Creep) {
+        //Back from a chase or a stun, it heads for the closest path point of its lane ahead of it
+        let reentry = lane_of(creep).and_then(|lane| lane.points.get(behaviour::lane_reentry(&lane.path, creep.position.xy())).copied());
+        if let Some(next_path_point) = reentry {
+            set_component(creep.model, components::next_path_point(), next_path_point);
+        }
 
-                let current_pos = entity::get_component(model, translation()).unwrap();
+        let next_path_point = entity::get_component(creep.model, components::next_path_point()).unwrap();
+        let target = get_component(next_path_point, translation()).unwrap();
+        entity::add_component(creep.model, components::target_pos(), target.xy());
+        //Back on its lane, the next chase is measured from wherever it starts
+        entity::remove_component(creep.model, components::leash_origin());
+    }
 
-                let target_pos = entity::get_component(model, components::target_pos()).unwrap();
+    fn update(&self, creep: &mut Creep) -> Option<StateId> {
+        let next_path_point = entity::get_component(creep.model, components::next_path_point()).unwrap();
+        let next_point = lane_of(creep).and_then(|lane| lane.points.iter().position(|point| *point == next_path_point)).unwrap_or(0);
+        act(creep, CreepState::Move { next_point })
+    }
 
-                let diff = target_pos - current_pos.xy();
+    fn exit(&self, creep: &mut Creep) {
+        entity::remove_component(creep.model, components::target_pos());
+    }
+}
 
-                if diff.length() < 1.0 {
+//Runs after the enemy picked by the move state, until it is in range, dead or out of sight
+struct PursuitState;
 
-                    move_character(model, vec3(0., 0., -0.1), 0.01, delta_time());
+impl State<Creep> for PursuitState {
+    fn name(&self) -> &'static str { "pursuit" }
 
-                    if anim_state != attack_animation_state!() {
-                        entity::set_component(
-                            anim_model,
-                            apply_animation_player(),
-                            idle_player.0,
-                        );
-                        entity::set_component(
-                            anim_model,
-                            components::anim_state(),
-                            idle_animation_state!(),
-                        );
+    fn enter(&self, creep: &mut Creep) {
+        if let Some(target) = creep.target {
+            entity::add_component(creep.model, pursuit_target(), target);
+        }
+    }
 
-                        let current_path_point = get_component(model, components::next_path_point()).unwrap();
+    fn update(&self, creep: &mut Creep) -> Option<StateId> {
+        let target = entity::get_component(creep.model, pursuit_target()).unwrap();
+        act(creep, CreepState::Pursuit(target))
+    }
 
-                        let next_path_point = match get_component(current_path_point, components::next_path_point()) {
-                            Some(next) => next,
-                            None => current_path_point
-                        };
+    fn exit(&self, creep: &mut Creep) {
+        entity::remove_component(creep.model, pursuit_target());
+    }
+}
 
-                        set_component(model, components::next_path_point(), next_path_point);
+//Hits the enemy caught by the pursuit state, or shoots it for ranged and siege creeps
+struct AttackState;
 
-                        let next_target = get_component(next_path_point, translation()).unwrap();
+impl State<Creep> for AttackState {
+    fn name(&self) -> &'static str { "attack" }
 
-                        entity::set_component(model, components::target_pos(), Vec2{x:next_target.x, y:next_target.y});
+    fn enter(&self, creep: &mut Creep) {
+        if let Some(target) = creep.target {
+            entity::add_component(creep.model, attack_target(), target);
+        }
+    }
 
-                        continue;
-                    }
-                }
+    fn update(&self, creep: &mut Creep) -> Option<StateId> {
+        let target = entity::get_component(creep.model, attack_target()).unwrap();
+        act(creep, CreepState::Attack(target))
+    }
+
+    fn exit(&self, creep: &mut Creep) {
+        entity::remove_component(creep.model, attack_target());
+        //Whatever comes next plays its own animation, the attack one must not keep going
+        set_idle_animation(creep);
+    }
+}
 
+//Walks back to where the creep left its lane after chasing something too far, or to its spot in the camp for monsters,
+//without looking at enemies on the way so it is not pulled away again
+struct ReturnState;
 
-                //-----------------------
+impl State<Creep> for ReturnState {
+    fn name(&self) -> &'static str { "return" }
 
-                let target_direction = diff;
-                let initial_direction: Vec2 = Vec2::new(1.0, 0.0);
-                let dot = initial_direction.dot(target_direction);
-                let det = initial_direction.x * target_direction.y
-                    - initial_direction.y * target_direction.x;
-                let angle = det.atan2(dot);
-                let rot: Quat = Quat::from_rotation_z(angle - INIT_POS);
-                entity::set_component(model, rotation(), rot);
+    fn update(&self, creep: &mut Creep) -> Option<StateId> {
+        act(creep, CreepState::Return)
+    }
+}
 
-                let speed = CREEP_MOVE_STATE_SPEED;
-                let displace = diff.normalize_or_zero() * speed;
+//Waits at the monster's spot in its camp until someone hits a monster of the camp. Back there, it is healed up and forgets who hit it
+struct CampState;
 
-                if anim_state != walk_animation_state!() {
-                    entity::set_component(anim_model, apply_animation_player(), walk_player.0);
-                    entity::set_component(
-                        anim_model,
-                        components::anim_state(),
-                        walk_animation_state!(),
-                    );
-                }
-                let collision = move_character(
-                    model,
-                    vec3(displace.x, displace.y, -0.1),
-                    0.01,
-                    delta_time(),
-                );
-
-                if collision.side {
-                    //commented out the target_pos change as it's breaking the path finding.
-                    /*entity::set_component(
-                        model,
-                        components::target_pos(),
-                        current_pos.xy(),
-                    );*/
-                    entity::set_component(anim_model, apply_animation_player(), idle_player.0);
-                    entity::set_component(
-                        anim_model,
-                        components::anim_state(),
-                        idle_animation_state!(),
-                    );
-                }
+impl State<Creep> for CampState {
+    fn name(&self) -> &'static str { "camp" }
+
+    fn enter(&self, creep: &mut Creep) {
+        if let Some(max_health) = entity::get_component(creep.model, components::max_health()) {
+            entity::set_component(creep.model, components::health(), max_health);
+        }
+        entity::remove_component(creep.model, components::last_attacker());
+        set_idle_animation(creep);
+    }
+
+    fn update(&self, creep: &mut Creep) -> Option<StateId> {
+        act(creep, CreepState::Camp)
+    }
+}
+
+//Stands still until the stun from the status effects module wears off, then goes back to its lane or camp
+struct StunnedState;
+
+impl State<Creep> for StunnedState {
+    fn name(&self) -> &'static str { "stunned" }
+
+    fn enter(&self, creep: &mut Creep) {
+        set_idle_animation(creep);
+    }
+
+    fn update(&self, creep: &mut Creep) -> Option<StateId> {
+        fall(creep);
+        None
+    }
+}
+
+//Plays the death animation and pays the bounty. There is no way out of it, the creep is despawned afterwards
+struct DeadState;
+
+impl State<Creep> for DeadState {
+    fn name(&self) -> &'static str { "dead" }
+
+    fn enter(&self, creep: &mut Creep) {
+        let creep_model = creep.model;
+        let anim_model = creep.anim_model;
+
+        entity::add_component(creep_model, components::is_dead(), ());
+
+        //Whoever landed the last hit gets the bounty
+        let killer = entity::get_component(creep_model, components::last_attacker()).unwrap_or(EntityId::null());
+        let bounty = entity::get_component(creep_model, components::bounty()).unwrap_or(0);
+        messages::UnitKilled::new(creep_model, killer, bounty).send_local_broadcast(false);
+
+        entity::set_component(anim_model, apply_animation_player(), creep.animations.death.0);
+        entity::set_component(anim_model, components::anim_state(), death_animation_state!());
+
+        run_async(async move {
+            sleep(CREEP_DEATH_ANIMATION_DURATION).await;
+            entity::despawn(anim_model);
+            entity::despawn(creep_model);
+        });
+    }
+}
+
+//The path points of the creep's lane, None for monsters
+fn lane_of(creep: &Creep) -> Option<&LanePath> {
+    let lane = entity::get_component(creep.model, components::lane())?;
+    creep.surroundings.lanes.get(&lane)
+}
+
+//Where the creep stands along its own lane
+fn position_on_lane(creep: &Creep) -> Option<LanePosition> {
+    lane_position(&lane_of(creep)?.path, creep.position.xy())
+}
+
+//Decides what the creep does this frame with the behaviour shared with the simulation, then does it: walks, hits or shoots.
+//Returns the state the creep goes to, when it leaves `state`
+fn act(creep: &mut Creep, state: CreepState<EntityId>) -> Option<StateId> {
+    let surroundings = creep.surroundings.clone();
+    let lane = entity::get_component(creep.model, components::lane()).and_then(|lane| surroundings.lanes.get(&lane));
+    let stats = entity::get_component(creep.model, components::stats()).map_or(StatBlock::ZERO, |stats| StatBlock::from_component(&stats));
+
+    let unit = behaviour::Creep {
+        id: creep.model,
+        team: creep.team,
+        position: creep.position.xy(),
+        radius: creep.radius,
+        stats,
+        attack_cooldown: entity::get_component(creep.model, components::attack_cooldown()).unwrap_or(0.),
+        leash_origin: entity::get_component(creep.model, components::leash_origin()),
+        lane: lane.map(|lane| lane.path.as_slice()),
+    };
+    let action = behaviour::creep_action(&unit, state, &surroundings.units, &*creep, delta_time());
+
+    match action.leash_origin {
+        Some(leash_origin) => entity::add_component(creep.model, components::leash_origin(), leash_origin),
+        None => entity::remove_component(creep.model, components::leash_origin()),
+    }
+    entity::set_component(creep.model, components::attack_cooldown(), action.attack_cooldown);
+
+    match (state, action.state) {
+        //Still walking its lane, it may have reached a path point and head for the next one. Coming back to it, the move state picks where to go
+        (CreepState::Move { .. }, CreepState::Move { next_point }) => {
+            if let Some(lane) = lane {
+                set_component(creep.model, components::next_path_point(), lane.points[next_point]);
+                set_component(creep.model, components::target_pos(), lane.path[next_point]);
             }
         }
-    });
+        //A hero fight or the death of what it was chasing gave it another target
+        (CreepState::Pursuit(previous), CreepState::Pursuit(target)) if previous != target => {
+            set_component(creep.model, pursuit_target(), target);
+        }
+        _ => {}
+    }
+
+    match action.state {
+        CreepState::Move { .. } | CreepState::Return => walk(creep, action.velocity, creep.animations.walk, walk_animation_state!()),
+        CreepState::Pursuit(_) => walk(creep, action.velocity, creep.animations.pursuit, pursuit_animation_state!()),
+        CreepState::Attack(target) => attack(creep, target, action.attack.is_some(), stats),
+        CreepState::Camp => fall(creep),
+    }
+
+    creep.target = match action.state {
+        CreepState::Pursuit(target) | CreepState::Attack(target) => Some(target),
+        _ => None,
+    };
+    let next_state = state_id(action.state);
+    (next_state != state_id(state)).then_some(next_state)
 }
 
-fn creep_pursuit_state_system(idle_player: AnimationPlayer, pursuit_player: AnimationPlayer){
-    //TECHNOLOGICAL DEBT: this code is REALLY similar to the move code from the move state, I should encapsulate it to reduce code duplication
-    query((components::is_creep(), pursuit_target())).each_frame({
-        move |list| {
-            for (model, (_, pursuit_target)) in list {
-                
-                let anim_model = entity::get_component(model, components::anim_model()).unwrap();
-                let anim_state = entity::get_component(anim_model, components::anim_state()).unwrap();
+fn state_id(state: CreepState<EntityId>) -> StateId {
+    match state {
+        CreepState::Move { .. } => CREEP_MOVE_STATE,
+        CreepState::Pursuit(_) => CREEP_PURSUIT_STATE,
+        CreepState::Attack(_) => CREEP_ATTACK_STATE,
+        CreepState::Return => CREEP_RETURN_STATE,
+        CreepState::Camp => CREEP_CAMP_STATE,
+    }
+}
 
-                if anim_state == attack_animation_state!() {
-                    continue;
-                }
+//What this creep goes after: a hero hitting an allied hero nearby, or else the closest enemy in sight of the first kind of the targeting priority in data/creeps.toml.
+//Towers and bases protected by other towers can't be hit, so they are ignored until those fall. Monsters only go after whoever hits their camp
+fn find_target(creep: &Creep) -> Option<EntityId> {
+    let surroundings = &creep.surroundings;
+    let sight_range = stat(creep.model, Stat::SightRange);
+
+    if let Some(camp) = creep.camp {
+        return camp_attacker(creep, camp, sight_range);
+    }
 
-                let current_pos = entity::get_component(model, translation()).unwrap();
+    //Lane creeps walk past the neutral monsters, only heroes pull them
+    aggressor_nearby(creep).or_else(|| surroundings.units.closest_enemy(creep.team, creep.position.xy(), sight_range))
+}
 
-                let target_pos = entity::get_component(pursuit_target, translation()).unwrap().xy();
+//An enemy hero in sight hitting an allied hero within aggro range of this creep
+fn aggressor_nearby(creep: &Creep) -> Option<EntityId> {
+    let surroundings = &creep.surroundings;
+    let sight_range = stat(creep.model, Stat::SightRange);
+
+    surroundings.hero_fights.iter()
+        .filter(|(attacker, attacker_team, victim_team, victim_position)| {
+            *attacker_team != creep.team
+                && *victim_team == creep.team
+                && (*victim_position - creep.position.xy()).length() <= surroundings.units.targeting().aggro_range
+                && is_alive(*attacker)
+                && entity::get_component(*attacker, translation()).map_or(false, |position| (position.xy() - creep.position.xy()).length() <= sight_range)
+        })
+        .map(|(attacker, _, _, _)| *attacker)
+        .next()
+}
 
-                let diff = target_pos - current_pos.xy();
+//The closest alive one in sight of those who last hit a monster of this camp
+fn camp_attacker(creep: &Creep, camp: u32, sight_range: f32) -> Option<EntityId> {
+    creep.surroundings.camp_attackers.get(&camp)?.iter()
+        .filter(|attacker| is_alive(**attacker))
+        .filter_map(|attacker| {
+            let distance = (entity::get_component(*attacker, translation())?.xy() - creep.position.xy()).length();
+            Some((*attacker, distance))
+        })
+        .filter(|(_, distance)| *distance <= sight_range)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(attacker, _)| attacker)
+}
 
-                if diff.length() < CREEP_MAXIMUM_ATTACK_CHECK_DISTANCE {
-                    //TECHNOLOGICAL DEBT: Add here a state switch preparation, and I also should make an attack_target component
-                    entity::add_component(model, attack_target(), pursuit_target);
-                    entity::set_component(model, creep_next_state(), CREEP_ATTACK_STATE);
+//Faces the target and hits it, or shoots it for ranged and siege creeps, when its attack is ready
+fn attack(creep: &Creep, target: EntityId, attacks: bool, stats: StatBlock) {
+    let Some(target_position) = entity::get_component(target, translation()) else {
+        return;
+    };
 
-                    continue;
-                } else if diff.length() > CREEP_MAXIMUM_PURSUIT_CHECK_DISTANCE {
-                    entity::set_component(model, creep_next_state(), CREEP_MOVE_STATE);
-                    
-                    continue;
-                }
+    entity::set_component(creep.model, rotation(), rotation_towards(target_position.xy() - creep.position.xy()));
+    fall(creep);
 
+    let anim_state = entity::get_component(creep.anim_model, components::anim_state()).unwrap();
+    if anim_state != attack_animation_state!() {
+        entity::set_component(creep.anim_model, apply_animation_player(), creep.animations.attack.0);
+        entity::set_component(creep.anim_model, components::anim_state(), attack_animation_state!());
+    }
 
+    if !attacks {
+        return;
+    }
 
-                //-----------------------
+    let attack_damage = stats[Stat::AttackDamage].round() as i32;
+
+    //Ranged and siege creeps shoot, the projectile deals the damage when it arrives
+    match entity::get_component(creep.model, components::projectile_speed()) {
+        Some(projectile_speed) => {
+            messages::SpawnProjectile::new(
+                creep.model,
+                target,
+                creep.position + vec3(0., 0., CREEP_PROJECTILE_LAUNCH_HEIGHT),
+                target_position,
+                projectile_speed,
+                attack_damage,
+                creep.team,
+            ).send_local_broadcast(false);
+        }
+        None => {
+            deal_damage(target, attack_damage, creep.model);
+        }
+    }
+}
 
-                let target_direction = diff;
-                let initial_direction: Vec2 = Vec2::new(1.0, 0.0);
-                let dot = initial_direction.dot(target_direction);
-                let det = initial_direction.x * target_direction.y
-                    - initial_direction.y * target_direction.x;
-                let angle = det.atan2(dot);
-                let rot: Quat = Quat::from_rotation_z(angle - INIT_POS);
-                entity::set_component(model, rotation(), rot);
+//Moves the creep with the velocity the shared behaviour steered it with, standing still when it is zero, like at the end of the lane
+fn walk(creep: &Creep, velocity: Vec2, animation: AnimationPlayer, animation_state: Vec<f32>) {
+    let anim_state = entity::get_component(creep.anim_model, components::anim_state()).unwrap();
 
-                let speed = CREEP_MOVE_STATE_SPEED;
-                let displace = diff.normalize_or_zero() * speed;
+    if velocity == Vec2::ZERO {
+        fall(creep);
+        if anim_state != idle_animation_state!() {
+            set_idle_animation(creep);
+        }
+        return;
+    }
 
-                if anim_state != pursuit_animation_state!() {
-                    entity::set_component(anim_model, apply_animation_player(), pursuit_player.0);
-                    entity::set_component(
-                        anim_model,
-                        components::anim_state(),
-                        pursuit_animation_state!(),
-                    );
-                }
-                let collision = move_character(
-                    model,
-                    vec3(displace.x, displace.y, -0.1),
-                    0.01,
-                    delta_time(),
-                );
-
-                if collision.side {
-                    //commented out the target_pos change as it's breaking the path finding.
-                    /*entity::set_component(
-                        model,
-                        components::target_pos(),
-                        current_pos.xy(),
-                    );*/
-                    entity::set_component(anim_model, apply_animation_player(), idle_player.0);
-                    entity::set_component(
-                        anim_model,
-                        components::anim_state(),
-                        idle_animation_state!(),
-                    );
-                }
+    entity::set_component(creep.model, rotation(), rotation_towards(velocity));
+
+    if anim_state != animation_state {
+        entity::set_component(creep.anim_model, apply_animation_player(), animation.0);
+        entity::set_component(creep.anim_model, components::anim_state(), animation_state);
+    }
+
+    let displace = velocity * delta_time();
+    move_character(
+        creep.model,
+        vec3(displace.x, displace.y, -FALL_SPEED * delta_time()),
+        0.01,
+        delta_time(),
+    );
+}
+
+//Keeps a creep that stands still on the ground
+fn fall(creep: &Creep) {
+    move_character(creep.model, vec3(0., 0., -FALL_SPEED * delta_time()), 0.01, delta_time());
+}
+
+fn set_idle_animation(creep: &Creep) {
+    entity::set_component(creep.anim_model, apply_animation_player(), creep.animations.idle.0);
+    entity::set_component(creep.anim_model, components::anim_state(), idle_animation_state!());
+}
+
+fn rotation_towards(direction: Vec2) -> Quat {
+    let initial_direction: Vec2 = Vec2::new(1.0, 0.0);
+    let dot = initial_direction.dot(direction);
+    let det = initial_direction.x * direction.y
+        - initial_direction.y * direction.x;
+    let angle = det.atan2(dot);
+    Quat::from_rotation_z(angle - INIT_POS)
+}
+
+fn animations_of(creep: EntityId, creep_animations: &CreepAnimationsByKind) -> CreepAnimationPlayers {
+    let kind = entity::get_component(creep, components::creep_kind()).unwrap();
+    creep_animations[kind as usize]
+}
+
+fn clears_creeps_on_match_reset() {
+    messages::MatchReset::subscribe(|_, _| {
+        let all_creeps = query(components::is_creep()).build().evaluate();
+        for (creep_model, _) in all_creeps {
+            if let Some(anim_model) = entity::get_component(creep_model, components::anim_model()) {
+                entity::despawn(anim_model);
             }
+            entity::despawn(creep_model);
         }
+
+        let wave_timer = WaveTimer::default();
+        entity::set_component(resources(), components::spawn_timer(), wave_timer.time_to_next_wave);
+        entity::set_component(resources(), components::wave_number(), wave_timer.wave_number);
+        let camps = entity::get_component(resources(), components::camp_respawn_timers()).unwrap_or_default();
+        entity::set_component(resources(), components::camp_respawn_timers(), vec![0.; camps.len()]);
     });
 }
 
-fn creep_attack_state_system(){
-    query((components::is_creep(), attack_target())).each_frame({
-        move |list| {
-            for (creep_model, (_, target_entity)) in list {
-                println!("Should be attacking {:?}", target_entity);
+//A camp comes back respawn_time seconds after its last monster died. Camps are filled when the match starts
+fn respawns_camps_system(creep_roster: CreepRoster, creep_animations: CreepAnimationsByKind, camps: Vec<CampDefinition>) {
+    let monsters_query = query((is_creep(), components::camp())).excludes(components::is_dead()).build();
+
+    ambient_api::messages::Frame::subscribe(move |_| {
+        let match_state = entity::get_component(resources(), components::match_state()).and_then(MatchState::from_u32);
+        if match_state != Some(MatchState::InProgress) {
+            return;
+        }
+
+        let monsters = monsters_query.evaluate();
+        let mut respawn_timers = entity::get_component(resources(), components::camp_respawn_timers()).unwrap();
+
+        for (index, camp) in camps.iter().enumerate() {
+            let camp_index = index as u32;
+            if monsters.iter().any(|(_, (_, monster_camp))| *monster_camp == camp_index) {
+                continue;
             }
+
+            if respawn_timers[index] > 0. {
+                respawn_timers[index] -= delta_time();
+                continue;
+            }
+
+            for (slot, kind) in camp.monsters.iter().enumerate() {
+                let monster_position = camp.monster_position(slot).extend(0.);
+                create_monster(*kind, creep_roster.kind(*kind), creep_animations[*kind as usize], monster_position, camp_index);
+            }
+            respawn_timers[index] = camp.respawn_time;
         }
+
+        entity::set_component(resources(), components::camp_respawn_timers(), respawn_timers);
     });
 }
 
+fn spawns_creeps_regularly_system(creep_roster: CreepRoster, creep_animations: CreepAnimationsByKind, formation: FormationDefinition) {
+    query((translation(), components::is_path_point(), components::is_creep_spawn_point(), components::lane())).each_frame({
+        move |list| {
+            //Creeps only come out while heroes are fighting
+            let match_state = entity::get_component(resources(), components::match_state()).and_then(MatchState::from_u32);
+            if match_state != Some(MatchState::InProgress) {
+                return;
+            }
 
+            let mut wave_timer = WaveTimer {
+                time_to_next_wave: entity::get_component(resources(), components::spawn_timer()).unwrap(),
+                wave_number: entity::get_component(resources(), components::wave_number()).unwrap(),
+            };
+            let wave_leaving = wave_timer.tick(delta_time(), &creep_roster.waves);
+            entity::set_component(resources(), components::spawn_timer(), wave_timer.time_to_next_wave);
+            entity::set_component(resources(), components::wave_number(), wave_timer.wave_number);
 
-fn spawns_creeps_regularly_system(idle_player:AnimationPlayer) {
-    query((translation(), components::is_path_point(), components::is_creep_spawn_point())).each_frame({
-        move |list| {
-            let time_to_next_creep_spawn = entity::get_component(resources(), components::spawn_timer()).unwrap();
+            if let Some(wave_number) = wave_leaving {
+                let wave = creep_roster.waves.composition(wave_number);
 
-            if time_to_next_creep_spawn <= 0. {
-                for (spawn_point_entity_id, (coordinates, _, which_team)) in list {
+                for (spawn_point_entity_id, (coordinates, _, which_team, lane)) in list {
                     let next_path_point = entity::get_component(spawn_point_entity_id, components::next_path_point()).unwrap();
-              
-                    match which_team {
-                        MARS_TEAM => {create_ranged_creep(coordinates, idle_player, next_path_point, MARS_TEAM);},
-                        JUPYTER_TEAM => {create_ranged_creep(coordinates, idle_player, next_path_point, JUPYTER_TEAM);},
-                        2_u32..=u32::MAX => panic!("Hang on, we have neutral spawns now?")
+
+                    //The wave lines up behind the spawn point, facing the lane, so its creeps don't spawn inside each other
+                    let next_path_point_position = entity::get_component(next_path_point, translation()).unwrap();
+                    let lane_forward = (next_path_point_position - coordinates).xy();
+
+                    for (slot, kind) in wave.iter().enumerate() {
+                        let creep_position = coordinates + formation.slot_offset(slot, lane_forward).extend(0.);
+                        let definition = creep_roster.kind(*kind);
+                        let animations = creep_animations[*kind as usize];
+
+                        match which_team {
+                            MARS_TEAM => {create_lane_creep(*kind, definition, animations, creep_position, next_path_point, MARS_TEAM, lane);},
+                            JUPYTER_TEAM => {create_lane_creep(*kind, definition, animations, creep_position, next_path_point, JUPYTER_TEAM, lane);},
+                            NEUTRAL_TEAM => panic!("Neutral monsters don't walk lanes, they come from the camps of data/map.toml"),
+                            3_u32..=u32::MAX => panic!("Hang on, we have a fourth team now?")
+                        }
                     }
                 }
-                entity::set_component(resources(), components::spawn_timer(), TIME_TO_NEXT_CREEP_SPAWNS);
-            }
-            else {
-                entity::set_component(resources(), components::spawn_timer(), time_to_next_creep_spawn - delta_time());
             }
         }
     });
 }
 
-fn create_ranged_creep(init_pos: Vec3, idle_player:AnimationPlayer, next_path_point:EntityId, which_team:u32) -> EntityId{
+fn create_lane_creep(kind: CreepKind, definition: &CreepDefinition, animations: CreepAnimationPlayers, init_pos: Vec3, next_path_point:EntityId, which_team:u32, lane:u32) -> EntityId{
+    let model = create_creep(kind, definition, animations, init_pos, which_team, CREEP_MOVE_STATE);
+
+    entity::add_component(model, components::next_path_point(), next_path_point);
+    entity::add_component(model, components::lane(), lane);
+    
+    let target = get_component(next_path_point, translation()).unwrap();
+
+    entity::add_component(model, components::target_pos(), Vec2{x:target.x, y:target.y});
+
+    model
+}
+
+//A neutral monster, waiting at its spot of the camp. It walks back there after every fight
+fn create_monster(kind: CreepKind, definition: &CreepDefinition, animations: CreepAnimationPlayers, init_pos: Vec3, camp: u32) -> EntityId{
+    let model = create_creep(kind, definition, animations, init_pos, NEUTRAL_TEAM, CREEP_CAMP_STATE);
+
+    entity::add_component(model, components::camp(), camp);
+    entity::add_component(model, components::leash_origin(), init_pos.xy());
+
+    model
+}
+
+//What lane creeps and monsters share, with the model of their team
+fn create_creep(kind: CreepKind, definition: &CreepDefinition, animations: CreepAnimationPlayers, init_pos: Vec3, which_team:u32, state: StateId) -> EntityId{
     let model = Entity::new()
         .with_merge(make_transformable())
         .with(translation(), vec3(init_pos.x, init_pos.y, init_pos.z))
         .with(character_controller_height(), 2.)
-        .with(character_controller_radius(), 0.3)
+        .with(character_controller_radius(), definition.radius)
         .with(dynamic(), true)
         .with_default(physics_controlled())
         .with_default(local_to_world())
         .with(rotation(), Quat::from_rotation_z(-INIT_POS))
-        .with(name(), "Ranged Creep".to_string())
-        .with(creep_current_state(), CREEP_MOVE_STATE)
-        .with(creep_next_state(), CREEP_MOVE_STATE)
+        .with(name(), definition.name.clone())
+        .with(creep_current_state(), state.0)
+        .with(components::creep_kind(), kind as u32)
+        .with(components::attack_cooldown(), 0.)
+        .with(components::base_stats(), definition.base_stats().to_component())
+        .with(components::stats(), definition.base_stats().to_component())
+        .with(components::health(), definition.health)
+        .with(components::max_health(), definition.health)
+        .with(components::bounty(), definition.bounty)
+        .with(components::experience_reward(), definition.experience)
         .spawn();
 
-    let mut creep_model_address = "";
-
-    match which_team{
-        MARS_TEAM => creep_model_address = "assets/model/copzombie_l_actisdato.fbx",
-        JUPYTER_TEAM => creep_model_address = "assets/model/X Bot.fbx",
-        2_u32..=u32::MAX => panic!("Hang on, we have neutral creeps now?")
+    if let Some(projectile_speed) = definition.projectile_speed {
+        entity::add_component(model, components::projectile_speed(), projectile_speed);
     }
+
+    let creep_model_address = match which_team{
+        MARS_TEAM | JUPYTER_TEAM | NEUTRAL_TEAM => match definition.models.get(which_team as usize) {
+            Some(address) => address,
+            None => panic!("{} has no model for team {} in data/creeps.toml", definition.name, which_team),
+        },
+        3_u32..=u32::MAX => panic!("Hang on, we have a fourth team now?")
+    };
     
 
     let anim_model = Entity::new()
@@ -488,18 +835,13 @@ fn create_ranged_creep(init_pos: Vec3, idle_player:AnimationPlayer, next_path_po
 
     entity::add_component(model, components::is_creep(), ());    
 
-    entity::add_component(anim_model, apply_animation_player(), idle_player.0);
+    entity::add_component(anim_model, apply_animation_player(), animations.idle.0);
     entity::add_component(anim_model, components::anim_state(), idle_animation_state!());
 
     entity::add_component(model, children(), vec![anim_model]);
     entity::add_component(model, components::anim_model(), anim_model);
-    entity::add_component(model, components::next_path_point(), next_path_point);
 
     entity::add_component(model, team(), which_team);
-    
-    let target = get_component(next_path_point, translation()).unwrap();
-
-    entity::add_component(model, components::target_pos(), Vec2{x:target.x, y:target.y});
 
     model
 }
\ No newline at end of file
@@ -0,0 +1,98 @@
+use ambient_api::{
+    components::core::player::{player, user_id},
+    prelude::*,
+};
+use amoba::match_state::MatchState;
+
+const STARTING_GOLD: u32 = 600;
+
+// every player gets PASSIVE_GOLD every PASSIVE_GOLD_INTERVAL seconds while the match is on
+const PASSIVE_GOLD: u32 = 2;
+const PASSIVE_GOLD_INTERVAL: f32 = 1.0;
+
+#[main]
+pub fn main() {
+    entity::add_component(
+        entity::resources(),
+        components::passive_gold_timer(),
+        PASSIVE_GOLD_INTERVAL,
+    );
+
+    spawn_query(player()).bind(|players| {
+        for (player_id, _) in players {
+            entity::add_component(player_id, components::gold(), STARTING_GOLD);
+        }
+    });
+
+    passive_income_system();
+
+    messages::UnitKilled::subscribe(|_, msg| {
+        pay_bounty(msg.unit, msg.killer, msg.bounty);
+    });
+
+    messages::MatchReset::subscribe(|_, _| {
+        for (player_id, _) in query(components::gold()).build().evaluate() {
+            entity::set_component(player_id, components::gold(), STARTING_GOLD);
+        }
+        entity::set_component(
+            entity::resources(),
+            components::passive_gold_timer(),
+            PASSIVE_GOLD_INTERVAL,
+        );
+    });
+}
+
+fn passive_income_system() {
+    query((player(), components::gold())).each_frame(|players| {
+        let match_state = entity::get_component(entity::resources(), components::match_state())
+            .and_then(MatchState::from_u32);
+        if match_state != Some(MatchState::InProgress) {
+            return;
+        }
+
+        let timer =
+            entity::get_component(entity::resources(), components::passive_gold_timer()).unwrap();
+        if timer > 0.0 {
+            entity::set_component(
+                entity::resources(),
+                components::passive_gold_timer(),
+                timer - delta_time(),
+            );
+            return;
+        }
+
+        for (player_id, (_, gold)) in players {
+            entity::set_component(player_id, components::gold(), gold + PASSIVE_GOLD);
+        }
+        entity::set_component(
+            entity::resources(),
+            components::passive_gold_timer(),
+            PASSIVE_GOLD_INTERVAL,
+        );
+    });
+}
+
+// only heroes earn bounties, a unit killed by a creep or a tower pays nobody
+fn pay_bounty(unit: EntityId, killer: EntityId, bounty: u32) {
+    if killer.is_null() || bounty == 0 {
+        return;
+    }
+
+    let owner = query((player(), components::hero_model()))
+        .build()
+        .evaluate()
+        .into_iter()
+        .find(|(_, (_, hero_model))| *hero_model == killer)
+        .map(|(player_id, _)| player_id);
+    let player_id = match owner {
+        Some(player_id) => player_id,
+        None => return,
+    };
+
+    let gold = entity::get_component(player_id, components::gold()).unwrap_or(0);
+    entity::add_component(player_id, components::gold(), gold + bounty);
+
+    if let Some(user) = entity::get_component(player_id, user_id()) {
+        messages::CoinSound::new(unit, killer, player_id).send_client_targeted_reliable(user);
+    }
+}
@@ -0,0 +1,20 @@
+//! Code shared by the client and server modules of amoba.
+//!
+//! Every module is its own binary, so anything more than one of them needs lives here.
+//! Nothing in this crate talks to the engine.
+
+pub mod behaviour;
+pub mod creeps;
+pub mod map_data;
+pub mod match_state;
+pub mod movement;
+pub mod pathfinding;
+pub mod roster;
+pub mod rules;
+pub mod shop;
+pub mod simulation;
+pub mod spatial;
+pub mod state_machine;
+pub mod stats;
+pub mod status_effects;
+pub mod steering;
@@ -25,26 +25,30 @@ pub fn main() {
 #[element_component]
 fn App(hooks: &mut Hooks) -> Element {
     let (show, set_show) = hooks.use_state(false);
-    let (role, set_role) = hooks.use_state(None);
+    let (team, set_team) = hooks.use_state(None);
     let (player_pos, set_player_pos) = hooks.use_state(vec2(0.0, 0.0));
     let (camera_pos, set_camera_pos) = hooks.use_state(vec2(0.0, 0.0));
     // let screen_size = hooks.use_query(window_logical_size());
     hooks.use_frame(move |w| {
         let local = player::get_local();
-        let role = w.get(local, components::role());
-        if let Ok(r) = role {
-            set_role(Some(r));
+        let team = w.get(local, components::team());
+        if let Ok(t) = team {
+            set_team(Some(t));
             set_show(true);
         } else {
             return;
         }
-        let model = w.get(local, components::hero_model()).unwrap();
+        let model = match w.get(local, components::hero_model()) {
+            Ok(model) => model,
+            // the hero died and its model is gone
+            Err(_) => return,
+        };
         let pos = w.get(model, translation()).unwrap();
         let pos = pos.xy();
         let cam = w.get(local, components::cam()).unwrap();
         let cam_look = w.get(cam, lookat_target()).unwrap();
         let cam_look = cam_look.xy();
-        match role.unwrap() % 2 {
+        match team.unwrap() {
             1 => {
                 let home = vec2(-15.0, -15.0);
                 let x = pos.y - home.x;
@@ -84,8 +88,8 @@ fn App(hooks: &mut Hooks) -> Element {
     // .with_margin_even(5.0);
 
     let (red_pos, blue_pos) = {
-        if let Some(role) = role {
-            match role % 2 {
+        if let Some(team) = team {
+            match team {
                 1 => (vec3(70.0, 5.0, -0.1), vec3(5.0, 70.0, -0.1)),
                 _ => (vec3(5.0, 70.0, -0.1), vec3(70.0, 5.0, -0.1)),
             }
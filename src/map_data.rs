@@ -0,0 +1,748 @@
+//! The map layout, loaded from `data/map.toml`.
+//!
+//! A map is a square ground with one base per team, named waypoints, creep spawn points
+//! and lanes. A lane starts at a spawn point and walks through its waypoints in order.
+//! Towers defend the lanes of their team and must fall in order before the base can be hit.
+//! Obstacles are walls and rocks nobody walks through. Camps are where neutral monsters
+//! wait, away from the lanes.
+
+use std::{collections::HashSet, fmt};
+
+use glam::Vec2;
+use serde::Deserialize;
+
+use crate::{
+    creeps::CreepKind,
+    stats::{Stat, StatBlock},
+};
+
+const MAP: &str = include_str!("../data/map.toml");
+
+/// Radius of the sphere of a base.
+pub const BASE_RADIUS: f32 = 1.0;
+/// Width of a tower, which is as deep as it is wide.
+pub const TOWER_WIDTH: f32 = 0.8;
+/// Team of the monsters of the camps, enemy of both Mars and Jupiter.
+pub const NEUTRAL_TEAM: u32 = 2;
+
+// monsters of a camp stand on a circle this big around its position
+const CAMP_RADIUS: f32 = 1.2;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MapData {
+    /// Length of a side of the ground, centered on the origin.
+    pub ground_size: f32,
+    pub bases: Vec<BaseDefinition>,
+    pub spawn_points: Vec<SpawnPointDefinition>,
+    #[serde(default)]
+    pub waypoints: Vec<WaypointDefinition>,
+    pub lanes: Vec<LaneDefinition>,
+    #[serde(default)]
+    pub formation: FormationDefinition,
+    #[serde(default)]
+    pub towers: Vec<TowerDefinition>,
+    #[serde(default)]
+    pub tower: TowerStats,
+    #[serde(default)]
+    pub obstacles: Vec<ObstacleDefinition>,
+    #[serde(default)]
+    pub camps: Vec<CampDefinition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BaseDefinition {
+    pub team: u32,
+    pub position: Vec2,
+    pub health: i32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpawnPointDefinition {
+    pub name: String,
+    pub team: u32,
+    pub position: Vec2,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WaypointDefinition {
+    pub name: String,
+    pub position: Vec2,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LaneDefinition {
+    pub name: String,
+    /// Name of the spawn point creeps of this lane come from. The lane belongs to its team.
+    pub spawn: String,
+    /// Names of the waypoints to walk through, in order.
+    pub waypoints: Vec<String>,
+}
+
+/// A tower standing on a lane, defending the lane's team.
+/// Towers of a lane are listed from the outermost one to the one closest to the base.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TowerDefinition {
+    /// Name of the lane the tower defends.
+    pub lane: String,
+    pub position: Vec2,
+}
+
+/// Stats shared by every tower.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TowerStats {
+    pub health: i32,
+    pub attack_range: f32,
+    pub attack_damage: i32,
+    /// Seconds between two shots.
+    pub attack_cooldown: f32,
+    /// Units per second of the tower's projectiles.
+    pub projectile_speed: f32,
+    /// Gold earned by the player who destroys the tower.
+    pub bounty: u32,
+}
+
+impl TowerStats {
+    /// A tower sees as far as it shoots.
+    pub fn base_stats(&self) -> StatBlock {
+        let mut stats = StatBlock::ZERO;
+        stats[Stat::AttackDamage] = self.attack_damage as f32;
+        stats[Stat::AttackRange] = self.attack_range;
+        stats[Stat::AttackInterval] = self.attack_cooldown;
+        stats[Stat::SightRange] = self.attack_range;
+        stats
+    }
+}
+
+impl Default for TowerStats {
+    fn default() -> Self {
+        Self {
+            health: 150,
+            attack_range: 6.,
+            attack_damage: 12,
+            attack_cooldown: 1.5,
+            projectile_speed: 14.,
+            bounty: 150,
+        }
+    }
+}
+
+/// A box units have to walk around.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ObstacleDefinition {
+    pub name: String,
+    /// Center of the obstacle.
+    pub position: Vec2,
+    /// Length of the obstacle along x and y.
+    pub size: Vec2,
+}
+
+impl ObstacleDefinition {
+    pub fn contains(&self, position: Vec2) -> bool {
+        let offset = (position - self.position).abs();
+        offset.x <= self.size.x / 2. && offset.y <= self.size.y / 2.
+    }
+}
+
+/// Neutral monsters that wait at their camp, fight back whoever attacks them and come back
+/// some time after they are all dead.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CampDefinition {
+    pub name: String,
+    pub position: Vec2,
+    /// Kind of each monster of the camp, which gives it the stats of the creeps of that kind.
+    pub monsters: Vec<CreepKind>,
+    /// Seconds between the death of the last monster of the camp and its return.
+    pub respawn_time: f32,
+}
+
+impl CampDefinition {
+    /// Where the `slot`-th monster of the camp stands, around the position of the camp.
+    pub fn monster_position(&self, slot: usize) -> Vec2 {
+        if self.monsters.len() <= 1 {
+            return self.position;
+        }
+        let angle = std::f32::consts::TAU * slot as f32 / self.monsters.len() as f32;
+        self.position + Vec2::from_angle(angle) * CAMP_RADIUS
+    }
+}
+
+/// How the creeps of a wave line up at their spawn point before walking the lane.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FormationDefinition {
+    /// Creeps per row.
+    pub columns: usize,
+    /// Distance between two creeps of the formation.
+    pub spacing: f32,
+}
+
+impl Default for FormationDefinition {
+    fn default() -> Self {
+        Self {
+            columns: 2,
+            spacing: 1.,
+        }
+    }
+}
+
+impl FormationDefinition {
+    /// Offset from the spawn point of the `slot`-th creep of a wave.
+    /// Rows of `columns` creeps are centered on the lane and stack up behind the spawn point,
+    /// `forward` being the direction the lane leaves in.
+    pub fn slot_offset(&self, slot: usize, forward: Vec2) -> Vec2 {
+        let forward = forward.normalize_or_zero();
+        let side = forward.perp();
+        let row = (slot / self.columns) as f32;
+        let column = (slot % self.columns) as f32 - (self.columns - 1) as f32 / 2.;
+        side * column * self.spacing - forward * row * self.spacing
+    }
+}
+
+/// Where a position stands along the path of a lane, see [`lane_position`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LanePosition {
+    /// Distance walked along the path to the point of the path closest to the position.
+    pub distance: f32,
+    /// Share of the path walked, from 0 at the spawn point to 1 at its last point.
+    pub progress: f32,
+    /// Index in the path of the first point ahead, never one already behind. The last
+    /// point once there.
+    pub next_point: usize,
+}
+
+/// Where `position` stands along `path`, as given by [`MapData::lane_path`]: it is brought
+/// back on the closest segment of the path, so a unit that wandered off the lane knows
+/// which point to walk to next without walking backwards. `None` if the path is empty.
+pub fn lane_position(path: &[Vec2], position: Vec2) -> Option<LanePosition> {
+    let last_point = path.len().checked_sub(1)?;
+    let length: f32 = path
+        .windows(2)
+        .map(|segment| segment[0].distance(segment[1]))
+        .sum();
+
+    let mut closest: Option<(f32, LanePosition)> = None;
+    let mut walked = 0.;
+    for (index, segment) in path.windows(2).enumerate() {
+        let along = segment[1] - segment[0];
+        let segment_length = along.length();
+        let t = match segment_length > 0. {
+            true => ((position - segment[0]).dot(along) / (segment_length * segment_length))
+                .clamp(0., 1.),
+            false => 0.,
+        };
+        let distance_to_lane = position.distance(segment[0] + along * t);
+        let closer = match closest {
+            Some((closest_distance, _)) => distance_to_lane < closest_distance,
+            None => true,
+        };
+        if closer {
+            let distance = walked + segment_length * t;
+            closest = Some((
+                distance_to_lane,
+                LanePosition {
+                    distance,
+                    progress: if length > 0. { distance / length } else { 0. },
+                    next_point: index + 1,
+                },
+            ));
+        }
+        walked += segment_length;
+    }
+
+    Some(match closest {
+        Some((_, lane_position)) => lane_position,
+        // a lane of a single point
+        None => LanePosition {
+            distance: 0.,
+            progress: 0.,
+            next_point: last_point,
+        },
+    })
+}
+
+/// Something wrong with a map. Errors make the map unplayable, warnings are just suspicious.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapIssue {
+    DuplicateName(String),
+    MissingBase { team: u32 },
+    DanglingSpawn { lane: String, spawn: String },
+    DanglingWaypoint { lane: String, waypoint: String },
+    EmptyLane { lane: String },
+    OutOfBounds { name: String },
+    EmptyFormation,
+    OverlappingSpawnPoints(String, String),
+    UnreachableWaypoint(String),
+    UnusedSpawnPoint(String),
+    DanglingTowerLane { lane: String },
+    InsideObstacle { name: String, obstacle: String },
+    EmptyCamp(String),
+}
+
+impl MapIssue {
+    pub fn is_error(&self) -> bool {
+        !matches!(
+            self,
+            MapIssue::OverlappingSpawnPoints(_, _)
+                | MapIssue::UnreachableWaypoint(_)
+                | MapIssue::UnusedSpawnPoint(_)
+        )
+    }
+}
+
+impl fmt::Display for MapIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapIssue::DuplicateName(name) => write!(f, "{name:?} is defined more than once"),
+            MapIssue::MissingBase { team } => write!(f, "team {team} has spawn points but no base"),
+            MapIssue::DanglingSpawn { lane, spawn } => {
+                write!(f, "lane {lane:?} starts at unknown spawn point {spawn:?}")
+            }
+            MapIssue::DanglingWaypoint { lane, waypoint } => {
+                write!(
+                    f,
+                    "lane {lane:?} goes through unknown waypoint {waypoint:?}"
+                )
+            }
+            MapIssue::EmptyLane { lane } => write!(f, "lane {lane:?} has no waypoints"),
+            MapIssue::OutOfBounds { name } => write!(f, "{name:?} is outside of the ground"),
+            MapIssue::EmptyFormation => write!(f, "the creep formation has no columns"),
+            MapIssue::OverlappingSpawnPoints(first, second) => write!(
+                f,
+                "spawn points {first:?} and {second:?} are so close their creeps will collide"
+            ),
+            MapIssue::UnreachableWaypoint(name) => {
+                write!(f, "waypoint {name:?} is not used by any lane")
+            }
+            MapIssue::UnusedSpawnPoint(name) => {
+                write!(f, "spawn point {name:?} is not used by any lane")
+            }
+            MapIssue::DanglingTowerLane { lane } => {
+                write!(f, "a tower defends unknown lane {lane:?}")
+            }
+            MapIssue::InsideObstacle { name, obstacle } => {
+                write!(f, "{name:?} is inside obstacle {obstacle:?}")
+            }
+            MapIssue::EmptyCamp(name) => write!(f, "camp {name:?} has no monsters"),
+        }
+    }
+}
+
+impl MapData {
+    /// The map shipped in `data/map.toml`. Panics if it does not parse or has errors,
+    /// warnings are left to the caller (see [`MapData::validate`]).
+    pub fn load() -> Self {
+        let map = match Self::parse(MAP) {
+            Ok(map) => map,
+            Err(error) => panic!("data/map.toml: {error}"),
+        };
+        let errors: Vec<String> = map
+            .validate()
+            .iter()
+            .filter(|issue| issue.is_error())
+            .map(|issue| issue.to_string())
+            .collect();
+        if !errors.is_empty() {
+            panic!("data/map.toml is not a valid map:\n{}", errors.join("\n"));
+        }
+        map
+    }
+
+    pub fn parse(source: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(source)
+    }
+
+    pub fn validate(&self) -> Vec<MapIssue> {
+        let mut issues = Vec::new();
+
+        let mut names = HashSet::new();
+        let named_positions = self
+            .spawn_points
+            .iter()
+            .map(|spawn| (&spawn.name, spawn.position))
+            .chain(
+                self.waypoints
+                    .iter()
+                    .map(|waypoint| (&waypoint.name, waypoint.position)),
+            )
+            .chain(self.camps.iter().map(|camp| (&camp.name, camp.position)));
+        for (name, position) in named_positions {
+            if !names.insert(name) {
+                issues.push(MapIssue::DuplicateName(name.clone()));
+            }
+            if !self.is_inside(position) {
+                issues.push(MapIssue::OutOfBounds { name: name.clone() });
+            }
+            // creeps walk from one of these to the next and monsters wait on them, they can't be in a wall
+            for obstacle in &self.obstacles {
+                if obstacle.contains(position) {
+                    issues.push(MapIssue::InsideObstacle {
+                        name: name.clone(),
+                        obstacle: obstacle.name.clone(),
+                    });
+                }
+            }
+        }
+
+        for obstacle in &self.obstacles {
+            if !names.insert(&obstacle.name) {
+                issues.push(MapIssue::DuplicateName(obstacle.name.clone()));
+            }
+            if !self.is_inside(obstacle.position) {
+                issues.push(MapIssue::OutOfBounds {
+                    name: obstacle.name.clone(),
+                });
+            }
+        }
+
+        for camp in &self.camps {
+            if camp.monsters.is_empty() {
+                issues.push(MapIssue::EmptyCamp(camp.name.clone()));
+            }
+        }
+
+        if self.formation.columns == 0 {
+            issues.push(MapIssue::EmptyFormation);
+        }
+
+        for (index, spawn) in self.spawn_points.iter().enumerate() {
+            for other in &self.spawn_points[index + 1..] {
+                if spawn.position.distance(other.position) < self.formation.spacing {
+                    issues.push(MapIssue::OverlappingSpawnPoints(
+                        spawn.name.clone(),
+                        other.name.clone(),
+                    ));
+                }
+            }
+        }
+
+        for spawn in &self.spawn_points {
+            if self.base_of_team(spawn.team).is_none()
+                && !issues.contains(&MapIssue::MissingBase { team: spawn.team })
+            {
+                issues.push(MapIssue::MissingBase { team: spawn.team });
+            }
+        }
+
+        let mut used_spawns = HashSet::new();
+        let mut used_waypoints = HashSet::new();
+        for lane in &self.lanes {
+            if self.spawn_point(&lane.spawn).is_none() {
+                issues.push(MapIssue::DanglingSpawn {
+                    lane: lane.name.clone(),
+                    spawn: lane.spawn.clone(),
+                });
+            }
+            used_spawns.insert(&lane.spawn);
+
+            if lane.waypoints.is_empty() {
+                issues.push(MapIssue::EmptyLane {
+                    lane: lane.name.clone(),
+                });
+            }
+            for waypoint in &lane.waypoints {
+                if self.waypoint(waypoint).is_none() {
+                    issues.push(MapIssue::DanglingWaypoint {
+                        lane: lane.name.clone(),
+                        waypoint: waypoint.clone(),
+                    });
+                }
+                used_waypoints.insert(waypoint);
+            }
+        }
+
+        for tower in &self.towers {
+            if self.lane(&tower.lane).is_none() {
+                issues.push(MapIssue::DanglingTowerLane {
+                    lane: tower.lane.clone(),
+                });
+            }
+            if !self.is_inside(tower.position) {
+                issues.push(MapIssue::OutOfBounds {
+                    name: format!("tower of lane {}", tower.lane),
+                });
+            }
+        }
+
+        for waypoint in &self.waypoints {
+            if !used_waypoints.contains(&waypoint.name) {
+                issues.push(MapIssue::UnreachableWaypoint(waypoint.name.clone()));
+            }
+        }
+        for spawn in &self.spawn_points {
+            if !used_spawns.contains(&spawn.name) {
+                issues.push(MapIssue::UnusedSpawnPoint(spawn.name.clone()));
+            }
+        }
+
+        issues
+    }
+
+    pub fn base_of_team(&self, team: u32) -> Option<&BaseDefinition> {
+        self.bases.iter().find(|base| base.team == team)
+    }
+
+    pub fn spawn_point(&self, name: &str) -> Option<&SpawnPointDefinition> {
+        self.spawn_points.iter().find(|spawn| spawn.name == name)
+    }
+
+    pub fn waypoint(&self, name: &str) -> Option<&WaypointDefinition> {
+        self.waypoints.iter().find(|waypoint| waypoint.name == name)
+    }
+
+    pub fn lane(&self, name: &str) -> Option<&LaneDefinition> {
+        self.lanes.iter().find(|lane| lane.name == name)
+    }
+
+    /// Index of a lane in the map, which is what the `lane` component holds.
+    pub fn lane_index(&self, name: &str) -> Option<usize> {
+        self.lanes.iter().position(|lane| lane.name == name)
+    }
+
+    /// Towers of a lane, from the outermost one to the one closest to the base.
+    pub fn towers_of_lane<'a>(
+        &'a self,
+        lane: &'a LaneDefinition,
+    ) -> impl Iterator<Item = &'a TowerDefinition> {
+        self.towers
+            .iter()
+            .filter(move |tower| tower.lane == lane.name)
+    }
+
+    /// Team the lane belongs to, which is the team of its spawn point.
+    pub fn lane_team(&self, lane: &LaneDefinition) -> u32 {
+        self.spawn_point(&lane.spawn).unwrap().team
+    }
+
+    /// Every point of a lane in walking order, starting with its spawn point.
+    pub fn lane_path(&self, lane: &LaneDefinition) -> Vec<Vec2> {
+        std::iter::once(self.spawn_point(&lane.spawn).unwrap().position)
+            .chain(
+                lane.waypoints
+                    .iter()
+                    .map(|waypoint| self.waypoint(waypoint).unwrap().position),
+            )
+            .collect()
+    }
+
+    /// Direction creeps leave a lane's spawn point in.
+    pub fn lane_forward(&self, lane: &LaneDefinition) -> Vec2 {
+        let path = self.lane_path(lane);
+        (path[1] - path[0]).normalize_or_zero()
+    }
+
+    fn is_inside(&self, position: Vec2) -> bool {
+        let half_size = self.ground_size / 2.;
+        position.x.abs() <= half_size && position.y.abs() <= half_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one lane of team 0 going through one waypoint, nothing wrong with it
+    const SMALL_MAP: &str = r#"
+        ground_size = 20.0
+
+        [[bases]]
+        team = 0
+        position = [-8.0, 0.0]
+        health = 100
+
+        [[spawn_points]]
+        name = "spawn"
+        team = 0
+        position = [-6.0, 0.0]
+
+        [[waypoints]]
+        name = "middle"
+        position = [0.0, 0.0]
+
+        [[lanes]]
+        name = "lane"
+        spawn = "spawn"
+        waypoints = ["middle"]
+
+        [[towers]]
+        lane = "lane"
+        position = [-3.0, 0.0]
+    "#;
+
+    fn small_map() -> MapData {
+        MapData::parse(SMALL_MAP).unwrap()
+    }
+
+    fn obstacle(name: &str, position: Vec2, size: Vec2) -> ObstacleDefinition {
+        ObstacleDefinition {
+            name: name.to_string(),
+            position,
+            size,
+        }
+    }
+
+    fn assert_issue(map: &MapData, issue: MapIssue) {
+        let issues = map.validate();
+        assert!(
+            issues.contains(&issue),
+            "expected {issue:?}, got {issues:?}"
+        );
+    }
+
+    #[test]
+    fn shipped_map_is_clean() {
+        assert_eq!(MapData::load().validate(), vec![]);
+    }
+
+    #[test]
+    fn small_map_is_clean() {
+        assert_eq!(small_map().validate(), vec![]);
+    }
+
+    #[test]
+    fn duplicate_name() {
+        let mut map = small_map();
+        map.waypoints[0].name = "spawn".to_string();
+        map.lanes[0].waypoints[0] = "spawn".to_string();
+        assert_issue(&map, MapIssue::DuplicateName("spawn".to_string()));
+    }
+
+    #[test]
+    fn missing_base() {
+        let mut map = small_map();
+        map.bases.clear();
+        assert_issue(&map, MapIssue::MissingBase { team: 0 });
+    }
+
+    #[test]
+    fn dangling_spawn() {
+        let mut map = small_map();
+        map.lanes[0].spawn = "nowhere".to_string();
+        assert_issue(
+            &map,
+            MapIssue::DanglingSpawn {
+                lane: "lane".to_string(),
+                spawn: "nowhere".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn dangling_waypoint() {
+        let mut map = small_map();
+        map.lanes[0].waypoints.push("nowhere".to_string());
+        assert_issue(
+            &map,
+            MapIssue::DanglingWaypoint {
+                lane: "lane".to_string(),
+                waypoint: "nowhere".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn empty_lane() {
+        let mut map = small_map();
+        map.lanes[0].waypoints.clear();
+        assert_issue(
+            &map,
+            MapIssue::EmptyLane {
+                lane: "lane".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut map = small_map();
+        map.waypoints[0].position = Vec2::new(0., 11.);
+        assert_issue(
+            &map,
+            MapIssue::OutOfBounds {
+                name: "middle".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn empty_formation() {
+        let mut map = small_map();
+        map.formation.columns = 0;
+        assert_issue(&map, MapIssue::EmptyFormation);
+    }
+
+    #[test]
+    fn overlapping_spawn_points() {
+        let mut map = small_map();
+        map.spawn_points.push(SpawnPointDefinition {
+            name: "other spawn".to_string(),
+            team: 0,
+            position: Vec2::new(-6., 0.5),
+        });
+        let issue =
+            MapIssue::OverlappingSpawnPoints("spawn".to_string(), "other spawn".to_string());
+        assert!(!issue.is_error());
+        assert_issue(&map, issue);
+    }
+
+    #[test]
+    fn unreachable_waypoint() {
+        let mut map = small_map();
+        map.waypoints.push(WaypointDefinition {
+            name: "forgotten".to_string(),
+            position: Vec2::new(0., 5.),
+        });
+        let issue = MapIssue::UnreachableWaypoint("forgotten".to_string());
+        assert!(!issue.is_error());
+        assert_issue(&map, issue);
+    }
+
+    #[test]
+    fn unused_spawn_point() {
+        let mut map = small_map();
+        map.spawn_points.push(SpawnPointDefinition {
+            name: "forgotten".to_string(),
+            team: 0,
+            position: Vec2::new(-6., 5.),
+        });
+        let issue = MapIssue::UnusedSpawnPoint("forgotten".to_string());
+        assert!(!issue.is_error());
+        assert_issue(&map, issue);
+    }
+
+    #[test]
+    fn dangling_tower_lane() {
+        let mut map = small_map();
+        map.towers[0].lane = "nowhere".to_string();
+        assert_issue(
+            &map,
+            MapIssue::DanglingTowerLane {
+                lane: "nowhere".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn inside_obstacle() {
+        let mut map = small_map();
+        map.obstacles
+            .push(obstacle("rock", Vec2::ZERO, Vec2::new(2., 2.)));
+        assert_issue(
+            &map,
+            MapIssue::InsideObstacle {
+                name: "middle".to_string(),
+                obstacle: "rock".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn empty_camp() {
+        let mut map = small_map();
+        map.camps.push(CampDefinition {
+            name: "camp".to_string(),
+            position: Vec2::new(0., 6.),
+            monsters: vec![],
+            respawn_time: 30.,
+        });
+        assert_issue(&map, MapIssue::EmptyCamp("camp".to_string()));
+    }
+}
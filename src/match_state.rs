@@ -0,0 +1,32 @@
+//! The lifecycle of a match: players join, pick their heroes, fight until a base falls,
+//! then everything is reset for the next game.
+
+/// Stored in the `match_state` resource as its `u32` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchState {
+    /// The server is up and nobody joined yet.
+    Lobby = 0,
+    /// Players are in, picking their team and hero.
+    PickPhase = 1,
+    /// Heroes are on the map, creeps spawn and the match clock runs.
+    InProgress = 2,
+    /// A base was destroyed, the winner is in the `match_winner` resource ([`NO_WINNER`] if
+    /// both fell together).
+    Finished = 3,
+}
+
+/// The `match_winner` of a match whose bases fell in the same frame, which nobody won.
+pub const NO_WINNER: u32 = u32::MAX;
+
+impl MatchState {
+    pub const ALL: [MatchState; 4] = [
+        MatchState::Lobby,
+        MatchState::PickPhase,
+        MatchState::InProgress,
+        MatchState::Finished,
+    ];
+
+    pub fn from_u32(state: u32) -> Option<Self> {
+        Self::ALL.get(state as usize).copied()
+    }
+}
@@ -0,0 +1,112 @@
+//! Walking speeds are in units per second, so units cover the same ground in the same time
+//! whatever the tick rate of the server.
+
+use glam::{Vec2, Vec3};
+
+/// How far a unit at `position` walking at `speed` units per second towards `target` goes
+/// in `dt` seconds. It stops on `target` instead of walking past it.
+///
+/// ```
+/// use amoba::movement::step_towards;
+/// use glam::Vec2;
+///
+/// // seconds to walk 10 units at 3 units per second, ticking `tick_rate` times per second
+/// fn travel_time(tick_rate: f32) -> f32 {
+///     let dt = 1.0 / tick_rate;
+///     let target = Vec2::new(10.0, 0.0);
+///     let mut position = Vec2::ZERO;
+///     let mut time = 0.0;
+///     while position.distance(target) > 0.001 {
+///         position += step_towards(position, target, 3.0, dt);
+///         time += dt;
+///     }
+///     time
+/// }
+///
+/// // same arrival, give or take the last tick
+/// for tick_rate in [20.0, 60.0, 144.0] {
+///     assert!((travel_time(tick_rate) - 10.0 / 3.0).abs() <= 1.0 / tick_rate);
+/// }
+/// ```
+pub fn step_towards(position: Vec2, target: Vec2, speed: f32, dt: f32) -> Vec2 {
+    let diff = target - position;
+    let distance = diff.length();
+    let step = speed * dt;
+    if step >= distance {
+        diff
+    } else {
+        diff / distance * step
+    }
+}
+
+/// Where a projectile at `position` homing on `target` at `speed` units per second is after `dt`
+/// seconds, or `None` once it gets there and hits.
+pub fn fly_towards(position: Vec3, target: Vec3, speed: f32, dt: f32) -> Option<Vec3> {
+    let diff = target - position;
+    let step = speed * dt;
+    match diff.length() > step {
+        true => Some(position + diff.normalize() * step),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // walks from the origin to `target` at `speed`, returning the time it took and every position on the way
+    fn walk(target: Vec2, speed: f32, tick_rate: f32) -> (f32, Vec<Vec2>) {
+        let dt = 1.0 / tick_rate;
+        let mut position = Vec2::ZERO;
+        let mut positions = vec![position];
+        let mut time = 0.0;
+        while position != target {
+            position += step_towards(position, target, speed, dt);
+            positions.push(position);
+            time += dt;
+            assert!(time < 60.0, "never got to {target}");
+        }
+        (time, positions)
+    }
+
+    #[test]
+    fn arrives_on_the_target_without_overshooting() {
+        let target = Vec2::new(3.0, 4.0);
+        let (_, positions) = walk(target, 2.0, 30.0);
+        for position in &positions {
+            assert!(position.distance(Vec2::ZERO) <= target.length() + 1e-5);
+        }
+        assert_eq!(*positions.last().unwrap(), target);
+
+        // one step bigger than what is left lands exactly on the target
+        assert_eq!(step_towards(Vec2::ZERO, target, 100.0, 1.0), target);
+        // and standing on it goes nowhere
+        assert_eq!(step_towards(target, target, 2.0, 1.0), Vec2::ZERO);
+    }
+
+    #[test]
+    fn projectile_hits_once_its_step_reaches_the_target() {
+        let target = Vec3::new(0.0, 0.0, 1.0);
+        assert_eq!(
+            fly_towards(Vec3::new(3.0, 0.0, 1.0), target, 2.0, 1.0),
+            Some(Vec3::new(1.0, 0.0, 1.0))
+        );
+        assert_eq!(
+            fly_towards(Vec3::new(1.0, 0.0, 1.0), target, 2.0, 1.0),
+            None
+        );
+    }
+
+    #[test]
+    fn same_travel_time_whatever_the_tick_rate() {
+        let target = Vec2::new(-6.0, 8.0);
+        let expected = target.length() / 4.0;
+        for tick_rate in [30.0, 60.0, 144.0] {
+            let (time, _) = walk(target, 4.0, tick_rate);
+            assert!(
+                (time - expected).abs() <= 1.0 / tick_rate,
+                "took {time}s at {tick_rate} Hz instead of {expected}s"
+            );
+        }
+    }
+}
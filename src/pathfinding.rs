@@ -0,0 +1,375 @@
+//! Paths around the bases, towers and obstacles of the map.
+//!
+//! The ground is cut into square cells, blocked when something stands on them. Paths are
+//! found with A* from cell to cell, then straightened so units walk from corner to corner
+//! instead of zigzagging along the grid.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use glam::Vec2;
+
+use crate::map_data::{MapData, BASE_RADIUS, TOWER_WIDTH};
+
+// cost of a move to a side neighbour and to a diagonal one, roughly 1 and the square root of 2
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkGrid {
+    /// Position of the corner of the first cell.
+    origin: Vec2,
+    cell_size: f32,
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+}
+
+impl WalkGrid {
+    /// An open grid covering a square of `size`, centered on the origin.
+    pub fn new(size: f32, cell_size: f32) -> Self {
+        let cells = (size / cell_size).ceil().max(1.) as usize;
+        Self {
+            origin: Vec2::splat(-size / 2.),
+            cell_size,
+            width: cells,
+            height: cells,
+            blocked: vec![false; cells * cells],
+        }
+    }
+
+    /// The ground of a map, with its bases, towers and obstacles blocked. They are made
+    /// `clearance` bigger on every side, so a unit of that radius does not brush them.
+    pub fn from_map(map: &MapData, cell_size: f32, clearance: f32) -> Self {
+        let towers: Vec<Vec2> = map.towers.iter().map(|tower| tower.position).collect();
+        Self::with_towers(map, cell_size, clearance, &towers)
+    }
+
+    /// Like [`WalkGrid::from_map`], with only the towers standing at `towers` blocked, so
+    /// the ground of the ones that fell can be walked on again.
+    pub fn with_towers(map: &MapData, cell_size: f32, clearance: f32, towers: &[Vec2]) -> Self {
+        let mut grid = Self::new(map.ground_size, cell_size);
+        for base in &map.bases {
+            grid.block_box(
+                base.position,
+                Vec2::splat(BASE_RADIUS * 2. + clearance * 2.),
+            );
+        }
+        for tower in towers {
+            grid.block_box(*tower, Vec2::splat(TOWER_WIDTH + clearance * 2.));
+        }
+        for obstacle in &map.obstacles {
+            grid.block_box(obstacle.position, obstacle.size + clearance * 2.);
+        }
+        grid
+    }
+
+    /// Blocks every cell the box of `size` centered on `center` touches.
+    pub fn block_box(&mut self, center: Vec2, size: Vec2) {
+        let (Some(min), Some(max)) = (
+            self.cell_clamped(center - size / 2.),
+            self.cell_clamped(center + size / 2.),
+        ) else {
+            return;
+        };
+        for y in min.1..=max.1 {
+            for x in min.0..=max.0 {
+                self.blocked[y * self.width + x] = true;
+            }
+        }
+    }
+
+    pub fn is_walkable(&self, position: Vec2) -> bool {
+        self.cell(position)
+            .is_some_and(|cell| !self.is_blocked(cell))
+    }
+
+    /// Points to walk through, in order, to go from `from` to `to`. The last one is `to`, or
+    /// the closest point to it that can be reached when it is blocked or walled off. `None`
+    /// when nothing can be walked on at all.
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        // a unit pushed against a wall may stand on a blocked cell, it steps out first
+        let start = match self.cell(from) {
+            Some(start) if !self.is_blocked(start) => start,
+            _ => self.closest_walkable(from)?,
+        };
+        let (goal, destination) = if self.is_walkable(to) {
+            (self.cell(to)?, to)
+        } else {
+            let goal = self.closest_walkable(to)?;
+            (goal, self.center(goal))
+        };
+
+        let cells = self.a_star(start, goal);
+
+        // the last cell becomes the exact destination, unless the path stops short of it
+        let mut points: Vec<Vec2> = cells.iter().map(|cell| self.center(*cell)).collect();
+        if cells.last() == Some(&goal) {
+            points.pop();
+            points.push(destination);
+        }
+
+        Some(self.straighten(from, points))
+    }
+
+    /// Whether a unit can walk the segment between `from` and `to` without entering a blocked cell.
+    pub fn line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        let steps = ((to - from).length() / (self.cell_size / 4.)).ceil() as usize;
+        (0..=steps).all(|step| {
+            let t = if steps == 0 {
+                1.
+            } else {
+                step as f32 / steps as f32
+            };
+            self.is_walkable(from.lerp(to, t))
+        })
+    }
+
+    // drops the points that can be skipped by walking in a straight line
+    fn straighten(&self, from: Vec2, points: Vec<Vec2>) -> Vec<Vec2> {
+        let mut straight = Vec::new();
+        let mut current = from;
+        let mut index = 0;
+        while index < points.len() {
+            let mut furthest = index;
+            while furthest + 1 < points.len() && self.line_of_sight(current, points[furthest + 1]) {
+                furthest += 1;
+            }
+            current = points[furthest];
+            straight.push(current);
+            index = furthest + 1;
+        }
+        straight
+    }
+
+    // cells from `start` to `goal`, or to the explored cell closest to it when it can't be reached
+    fn a_star(&self, start: (usize, usize), goal: (usize, usize)) -> Vec<(usize, usize)> {
+        let mut open = BinaryHeap::new();
+        let mut came_from = HashMap::new();
+        let mut cost = HashMap::new();
+        cost.insert(start, 0);
+        open.push(Reverse((heuristic(start, goal), start)));
+        let mut closest = start;
+
+        while let Some(Reverse((_, cell))) = open.pop() {
+            if cell == goal {
+                closest = cell;
+                break;
+            }
+            if heuristic(cell, goal) < heuristic(closest, goal) {
+                closest = cell;
+            }
+
+            let cell_cost = cost[&cell];
+            for (neighbour, step_cost) in self.neighbours(cell) {
+                let neighbour_cost = cell_cost + step_cost;
+                let better = match cost.get(&neighbour) {
+                    Some(known) => neighbour_cost < *known,
+                    None => true,
+                };
+                if better {
+                    cost.insert(neighbour, neighbour_cost);
+                    came_from.insert(neighbour, cell);
+                    open.push(Reverse((
+                        neighbour_cost + heuristic(neighbour, goal),
+                        neighbour,
+                    )));
+                }
+            }
+        }
+
+        let mut path = vec![closest];
+        let mut cell = closest;
+        while let Some(previous) = came_from.get(&cell) {
+            cell = *previous;
+            path.push(cell);
+        }
+        path.reverse();
+        path
+    }
+
+    // walkable cells around `cell`. Diagonals can't cut the corner of a blocked cell
+    fn neighbours(&self, cell: (usize, usize)) -> impl Iterator<Item = ((usize, usize), u32)> + '_ {
+        let (x, y) = (cell.0 as i64, cell.1 as i64);
+        let open = move |x: i64, y: i64| {
+            x >= 0
+                && y >= 0
+                && (x as usize) < self.width
+                && (y as usize) < self.height
+                && !self.is_blocked((x as usize, y as usize))
+        };
+        [
+            (-1, 0),
+            (1, 0),
+            (0, -1),
+            (0, 1),
+            (-1, -1),
+            (-1, 1),
+            (1, -1),
+            (1, 1),
+        ]
+        .into_iter()
+        .filter(move |(dx, dy)| {
+            open(x + dx, y + dy) && (*dx == 0 || *dy == 0 || (open(x + dx, y) && open(x, y + dy)))
+        })
+        .map(move |(dx, dy)| {
+            let step_cost = if dx == 0 || dy == 0 {
+                STRAIGHT_COST
+            } else {
+                DIAGONAL_COST
+            };
+            (((x + dx) as usize, (y + dy) as usize), step_cost)
+        })
+    }
+
+    fn closest_walkable(&self, position: Vec2) -> Option<(usize, usize)> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|cell| !self.is_blocked(*cell))
+            .min_by(|first, second| {
+                let first = self.center(*first).distance_squared(position);
+                let second = self.center(*second).distance_squared(position);
+                first.total_cmp(&second)
+            })
+    }
+
+    fn is_blocked(&self, cell: (usize, usize)) -> bool {
+        self.blocked[cell.1 * self.width + cell.0]
+    }
+
+    fn cell(&self, position: Vec2) -> Option<(usize, usize)> {
+        let local = (position - self.origin) / self.cell_size;
+        if local.x < 0. || local.y < 0. {
+            return None;
+        }
+        let (x, y) = (local.x as usize, local.y as usize);
+        (x < self.width && y < self.height).then_some((x, y))
+    }
+
+    // like `cell`, but positions off the grid give the closest cell on its border
+    fn cell_clamped(&self, position: Vec2) -> Option<(usize, usize)> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        let local = ((position - self.origin) / self.cell_size).max(Vec2::ZERO);
+        Some((
+            (local.x as usize).min(self.width - 1),
+            (local.y as usize).min(self.height - 1),
+        ))
+    }
+
+    fn center(&self, cell: (usize, usize)) -> Vec2 {
+        self.origin + (Vec2::new(cell.0 as f32, cell.1 as f32) + 0.5) * self.cell_size
+    }
+}
+
+// octile distance, never more than the real cost of the path
+fn heuristic(from: (usize, usize), to: (usize, usize)) -> u32 {
+    let dx = from.0.abs_diff(to.0) as u32;
+    let dy = from.1.abs_diff(to.1) as u32;
+    STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a 20 by 20 ground of unit cells with a 6 by 6 box in its middle
+    fn grid_with_box() -> WalkGrid {
+        let mut grid = WalkGrid::new(20., 1.);
+        grid.block_box(Vec2::ZERO, Vec2::splat(6.));
+        grid
+    }
+
+    fn assert_walkable(grid: &WalkGrid, from: Vec2, path: &[Vec2]) {
+        let mut current = from;
+        for point in path {
+            assert!(
+                grid.line_of_sight(current, *point),
+                "{current} to {point} goes through a blocked cell"
+            );
+            current = *point;
+        }
+    }
+
+    #[test]
+    fn goes_around_a_box() {
+        let grid = grid_with_box();
+        let (from, to) = (Vec2::new(-6., 0.5), Vec2::new(6., 0.5));
+        assert!(!grid.line_of_sight(from, to));
+
+        let path = grid.find_path(from, to).unwrap();
+        assert_eq!(*path.last().unwrap(), to);
+        assert!(path.len() >= 2, "walked straight through the box: {path:?}");
+        assert_walkable(&grid, from, &path);
+    }
+
+    #[test]
+    fn straight_line_when_nothing_is_in_the_way() {
+        let grid = grid_with_box();
+        let (from, to) = (Vec2::new(-8., -8.), Vec2::new(8., -7.));
+        assert_eq!(grid.find_path(from, to), Some(vec![to]));
+    }
+
+    #[test]
+    fn unreachable_goal_gets_as_close_as_possible() {
+        // a wall cutting the ground in two
+        let mut grid = WalkGrid::new(20., 1.);
+        grid.block_box(Vec2::ZERO, Vec2::new(1., 20.));
+        let (from, to) = (Vec2::new(-5., 0.), Vec2::new(5., 0.));
+        let path = grid.find_path(from, to).unwrap();
+        assert_walkable(&grid, from, &path);
+        // it stops against the wall, on the cell right across from the goal
+        assert_eq!(*path.last().unwrap(), Vec2::new(-1.5, 0.5));
+
+        // a goal inside the box is brought to a walkable cell next to it
+        let grid = grid_with_box();
+        let path = grid.find_path(Vec2::new(-8., 0.5), Vec2::ZERO).unwrap();
+        let end = *path.last().unwrap();
+        assert!(grid.is_walkable(end));
+        assert!(end.distance(Vec2::ZERO) < 4.5, "stopped at {end}");
+    }
+
+    #[test]
+    fn steps_out_of_a_blocked_start() {
+        let grid = grid_with_box();
+        let from = Vec2::new(-2.8, 0.5);
+        assert!(!grid.is_walkable(from));
+
+        let to = Vec2::new(-8., 0.5);
+        let path = grid.find_path(from, to).unwrap();
+        assert_eq!(*path.last().unwrap(), to);
+        // past its first point it only walks on open ground
+        assert!(grid.is_walkable(path[0]));
+        assert_walkable(&grid, path[0], &path[1..]);
+    }
+
+    #[test]
+    fn straightened_path_still_avoids_blocked_cells() {
+        // a zigzag of walls with a gap at alternating ends
+        let mut grid = WalkGrid::new(20., 1.);
+        grid.block_box(Vec2::new(-3.5, 2.), Vec2::new(1., 14.));
+        grid.block_box(Vec2::new(0.5, -2.), Vec2::new(1., 14.));
+        grid.block_box(Vec2::new(4.5, 2.), Vec2::new(1., 14.));
+        let from = Vec2::new(-8., 0.5);
+        let to = Vec2::new(8., 0.5);
+
+        let path = grid.find_path(from, to).unwrap();
+        assert_eq!(*path.last().unwrap(), to);
+        assert_walkable(&grid, from, &path);
+        // straightening kept only the corners, far fewer points than cells walked
+        assert!(path.len() <= 8, "{} points: {path:?}", path.len());
+    }
+
+    #[test]
+    fn fallen_towers_can_be_walked_on() {
+        let map = MapData::load();
+        let tower = map.towers[0].position;
+        assert!(!WalkGrid::from_map(&map, 0.5, 0.3).is_walkable(tower));
+
+        let others: Vec<Vec2> = map.towers[1..].iter().map(|tower| tower.position).collect();
+        assert!(WalkGrid::with_towers(&map, 0.5, 0.3, &others).is_walkable(tower));
+    }
+}
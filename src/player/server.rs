@@ -19,52 +19,113 @@ use ambient_api::{
     entity::add_component,
     prelude::*,
 };
+use amoba::{
+    map_data::{MapData, NEUTRAL_TEAM},
+    movement::step_towards,
+    pathfinding::WalkGrid,
+    roster::{HeroAnimations, HeroRoster},
+    stats::Stat,
+};
+
+#[path = "../server_common.rs"]
+mod server_common;
+use server_common::{deal_damage, is_alive, is_enemy, is_targetable, stat};
 
 const INIT_POS: f32 = std::f32::consts::FRAC_PI_2;
 
-macro_rules! idle_animation_state { () => { vec![1.0, 0.0, 0.0] }; }
-macro_rules! walk_animation_state { () => { vec![0.0, 1.0, 0.0] }; }
-macro_rules! attack_animation_state { () => { vec![0.0, 0.0, 1.0] }; }
+// respawn countdown = base + per minute of match, so late deaths cost more
+const HERO_BASE_RESPAWN_TIME: f32 = 5.0;
+const HERO_RESPAWN_TIME_PER_MINUTE: f32 = 2.0;
+const HERO_MAX_RESPAWN_TIME: f32 = 60.0;
+
+// the swing lasts HERO_ATTACK_ANIMATION_DURATION and the torch hits at HERO_ATTACK_HIT_TIME
+const HERO_ATTACK_ANIMATION_DURATION: f32 = 1.0;
+const HERO_ATTACK_HIT_TIME: f32 = 0.5;
+// the target may step back a little during the swing and still get hit
+const HERO_ATTACK_RANGE_TOLERANCE: f32 = 0.5;
+
+// units per second heroes are pushed down with, so they stay on the ground
+const FALL_SPEED: f32 = 6.0;
+
+// heroes walk around obstacles on a grid of cells this big
+const PATH_GRID_CELL_SIZE: f32 = 0.5;
+// a point of the path counts as reached this close to it
+const PATH_POINT_REACHED_DISTANCE: f32 = 0.3;
+// a hero chasing something plans its path again once its target moved this far
+const PATH_REPLAN_DISTANCE: f32 = 1.0;
+
+// enemy heroes this close to a dying unit share its experience
+const EXPERIENCE_RADIUS: f32 = 12.0;
+
+macro_rules! idle_animation_state { () => { vec![1.0, 0.0, 0.0, 0.0] }; }
+macro_rules! walk_animation_state { () => { vec![0.0, 1.0, 0.0, 0.0] }; }
+macro_rules! attack_animation_state { () => { vec![0.0, 0.0, 1.0, 0.0] }; }
+macro_rules! death_animation_state { () => { vec![0.0, 0.0, 0.0, 1.0] }; }
+
+/// Animation players of one hero of the roster, indexed by role like the roster itself.
+#[derive(Clone, Copy)]
+struct HeroAnimationPlayers {
+    idle: AnimationPlayer,
+    walk: AnimationPlayer,
+    attack: AnimationPlayer,
+    death: AnimationPlayer,
+}
+
+impl HeroAnimationPlayers {
+    fn load(animations: &HeroAnimations) -> Self {
+        let clip = |url: &str| PlayClipFromUrlNode::new(asset::url(url).unwrap());
+        Self {
+            idle: AnimationPlayer::new(&clip(&animations.idle)),
+            walk: AnimationPlayer::new(&clip(&animations.walk)),
+            attack: AnimationPlayer::new(&clip(&animations.attack)),
+            death: AnimationPlayer::new(&clip(&animations.death)),
+        }
+    }
+}
 
 #[main]
 pub fn main() {
-    messages::ChooseRole::subscribe(|source, msg| {
-        let idle = PlayClipFromUrlNode::new(
-            asset::url("assets/anim/Idle.fbx/animations/mixamo.com.anim").unwrap(),
-        );
-        let walk = PlayClipFromUrlNode::new(
-            asset::url("assets/anim/Walking.fbx/animations/mixamo.com.anim").unwrap(),
-        );
-        let attack = PlayClipFromUrlNode::new(
-            asset::url("assets/anim/Standing Torch Melee Attack 01.fbx/animations/mixamo.com.anim")
-                .unwrap(),
-        );
-        let idle_player = AnimationPlayer::new(&idle);
-        let walk_player = AnimationPlayer::new(&walk);
-        let attack_player = AnimationPlayer::new(&attack);
+    let roster = HeroRoster::load();
+    let map = MapData::load();
+    let hero_animations: Vec<HeroAnimationPlayers> = roster
+        .heroes
+        .iter()
+        .map(|hero| HeroAnimationPlayers::load(&hero.animations))
+        .collect();
 
+    // registered once here instead of in ChooseRole, otherwise every new player
+    // would add another copy of the movement loop
+    // the widest hero must fit through the gaps of the grid, so every hero does
+    let hero_radius = roster.heroes.iter().map(|hero| hero.radius).fold(0.0, f32::max);
+    hero_movement_system(map.clone(), hero_radius, hero_animations.clone());
+    hero_death_system(hero_animations.clone());
+    hero_respawn_system(map.clone(), hero_animations.clone());
+
+    messages::UnitKilled::subscribe({
+        let roster = roster.clone();
+        move |_, msg| {
+            share_experience(&roster, msg.unit, msg.killer);
+        }
+    });
+
+    messages::ChooseRole::subscribe(move |source, msg| {
         // this is waiting for the ui server module to send a message
         println!("{:?} chose role {:?} in player module", source, msg.role);
 
         let player_id = msg.player_id;
         let role = msg.role;
 
-        let minus = match role % 2 {
-            0 => 1.0,
-            _ => -1.0,
-        };
-        let init_pos = match role {
-            0 => {
-                // Mars Hero A
-                // set model, init pos
-                vec2(15.0, 15.0) - random::<Vec2>() * 3.0
-            }
-            1 => {
-                // Jupyter Hero A
-                vec2(-15.0, -15.0) + random::<Vec2>() * 3.0
+        let hero = match roster.hero(role) {
+            Some(hero) => hero,
+            None => {
+                println!("{:?} chose role {:?}, which is not in the hero roster", source, role);
+                return;
             }
-            _ => unimplemented!(),
         };
+        let animations = hero_animations[role as usize];
+
+        let minus = camera_side(hero.team);
+        let init_pos = spawn_position(&map, hero.team);
 
         let cam = Entity::new()
             .with_merge(make_perspective_infinite_reverse_camera())
@@ -81,11 +142,19 @@ pub fn main() {
             .with_merge(make_transformable())
             .with(translation(), vec3(init_pos.x, init_pos.y, 3.0))
             .with(character_controller_height(), 2.0)
-            .with(character_controller_radius(), 0.5)
+            .with(character_controller_radius(), hero.radius)
             .with_default(physics_controlled())
             .with_default(local_to_world())
             .with(rotation(), Quat::from_rotation_z(-INIT_POS))
-            .with(name(), "Hero".to_string())
+            .with(name(), hero.name.clone())
+            .with(components::health(), hero.health)
+            .with(components::max_health(), hero.health)
+            .with(components::bounty(), hero.bounty)
+            .with(components::experience_reward(), hero.experience)
+            .with(components::team(), hero.team)
+            .with(components::attack_cooldown(), 0.0)
+            .with(components::base_stats(), hero.base_stats().to_component())
+            .with(components::stats(), hero.base_stats().to_component())
             .spawn();
         let anim_model = Entity::new()
             .with_merge(make_transformable())
@@ -93,107 +162,25 @@ pub fn main() {
             .with(parent(), model)
             .with(
                 prefab_from_url(),
-                asset::url("assets/model/Ganfaul M Aure.fbx").unwrap(),
+                asset::url(&hero.model).unwrap(),
             )
             .with_default(local_to_parent())
             .with_default(local_to_world())
             .with(translation(), vec3(0.0, 0.0, 0.8))
             .spawn();
-        add_component(anim_model, apply_animation_player(), idle_player.0);
+        add_component(anim_model, apply_animation_player(), animations.idle.0);
         entity::add_component(anim_model, components::anim_state(), idle_animation_state!());
 
         entity::add_component(model, children(), vec![anim_model]);
         entity::add_component(player_id, components::role(), role);
+        entity::add_component(player_id, components::team(), hero.team);
         entity::add_component(player_id, components::hero_model(), model);
         entity::add_component(player_id, components::cam(), cam);
         // entity::add_component(player_id, components::ground(), ground);
         entity::add_component(player_id, components::anim_model(), anim_model);
         entity::add_component(player_id, components::target_pos(), init_pos);
-        query((player(), components::hero_model())).each_frame({
-            move |list| {
-
-                for (player_id, (_, model)) in list {
-                    let anim_model =
-                        entity::get_component(player_id, components::anim_model()).unwrap();
-                    let anim_state =
-                        entity::get_component(anim_model, components::anim_state()).unwrap();
-
-                    if anim_state == attack_animation_state!() {
-                        continue;
-                    }
-                    let current_pos = entity::get_component(model, translation()).unwrap();
-                    let target_pos =
-                        entity::get_component(player_id, components::target_pos()).unwrap();
-                    let diff = target_pos - current_pos.xy();
-
-                    if diff.length() < 1.0 {
-                        // if current_pos.xy() == vec2(0.0, 0.0) {
-                        physics::move_character(model, vec3(0., 0., -0.1), 0.01, delta_time());
-                        // }
-                        if entity::get_component(anim_model, components::anim_state()).unwrap()
-                            != attack_animation_state!()
-                        {
-                            entity::set_component(
-                                anim_model,
-                                apply_animation_player(),
-                                idle_player.0,
-                            );
-                            entity::set_component(
-                                anim_model,
-                                components::anim_state(),
-                                idle_animation_state!(),
-                            );
-                        };
-                        continue;
-                    }
-
-                    let target_direction = diff;
-                    let initial_direction: Vec2 = Vec2::new(1.0, 0.0);
-                    let dot = initial_direction.dot(target_direction);
-                    let det = initial_direction.x * target_direction.y
-                        - initial_direction.y * target_direction.x;
-                    let angle = det.atan2(dot);
-                    let rot: Quat = Quat::from_rotation_z(angle - INIT_POS);
-                    entity::set_component(model, rotation(), rot);
-
-                    let speed = 0.05;
-                    let displace = diff.normalize_or_zero() * speed;
-
-                    if anim_state != walk_animation_state!() {
-                        entity::set_component(anim_model, apply_animation_player(), walk_player.0);
-                        entity::set_component(
-                            anim_model,
-                            components::anim_state(),
-                            walk_animation_state!(),
-                        );
-                    }
-                    let collision = physics::move_character(
-                        model,
-                        vec3(displace.x, displace.y, -0.1),
-                        0.01,
-                        delta_time(),
-                    );
-
-                    if collision.side {
-                        entity::set_component(
-                            player_id,
-                            components::target_pos(),
-                            current_pos.xy(),
-                        );
-                        entity::set_component(anim_model, apply_animation_player(), idle_player.0);
-                        entity::set_component(
-                            anim_model,
-                            components::anim_state(),
-                            vec![1.0, 0.0, 0.0],
-                        );
-                    } else {
-                        let cam = entity::get_component(player_id, components::cam()).unwrap();
-                        // messages::PlayerWalk::new(model, cam, player_id)
-                        // .send_client_broadcast_unreliable();
-                    }
-                }
-            }
-        });
+        entity::add_component(player_id, components::experience(), 0);
+        entity::add_component(player_id, components::level(), 1);
     });
     messages::MouseLeftClick::subscribe(|_, msg| {
         let result = physics::raycast_first(msg.ray_origin, msg.ray_dir);
@@ -201,6 +188,8 @@ pub fn main() {
             if entity::has_component(hit.entity, plane_collider()) {
                 let target_pos = vec2(hit.position.x, hit.position.y);
                 entity::set_component(msg.player_id, components::target_pos(), target_pos);
+                // walking somewhere else cancels the current attack order
+                entity::remove_component(msg.player_id, components::hero_attack_target());
                 let crossx = make_transformable()
                     .with_default(quad())
                     .with(scale(), vec3(0.05, 0.3, 0.1))
@@ -222,14 +211,33 @@ pub fn main() {
         }
     });
 
+    messages::MouseRightClick::subscribe(|source, msg| {
+        // only the ui module's relay, with the sender's id, says whose hero attacks
+        if source.local().is_none() {
+            return;
+        }
+        let model = match entity::get_component(msg.player_id, components::hero_model()) {
+            Some(model) => model,
+            None => return,
+        };
+        let team = entity::get_component(model, components::team()).unwrap();
+        let result = physics::raycast_first(msg.ray_origin, msg.ray_dir);
+        if let Some(hit) = result {
+            if hit.entity != model && is_enemy(team, hit.entity) && is_targetable(hit.entity) {
+                entity::add_component(msg.player_id, components::hero_attack_target(), hit.entity);
+            }
+        }
+    });
+
     messages::CamMove::subscribe(|_, msg| {
         // println!("cam move {:?}", msg);
-        let c = entity::get_component(msg.player_id, components::cam()).unwrap();
-        let role = entity::get_component(msg.player_id, components::role()).unwrap();
-        let minus = match role % 2 {
-            0 => 1.0,
-            _ => -1.0,
+        // the camera is gone if the match was reset while this message was on its way
+        let c = match entity::get_component(msg.player_id, components::cam()) {
+            Some(c) => c,
+            None => return,
         };
+        let team = entity::get_component(msg.player_id, components::team()).unwrap();
+        let minus = camera_side(team);
         let x = msg.direction.x;
         let y = msg.direction.y;
 
@@ -239,4 +247,420 @@ pub fn main() {
         let pos = entity::get_component(c, translation()).unwrap();
         entity::set_component(c, translation(), pos + vec3(y, x, 0.0) * -0.1 * minus);
     });
+
+    // back to the pick screen, heroes and their cameras go away with the match
+    messages::MatchReset::subscribe(|_, _| {
+        for (player_id, _) in query(player()).build().evaluate() {
+            remove_hero(player_id);
+        }
+    });
+}
+
+fn remove_hero(player_id: EntityId) {
+    for entity_component in [components::hero_model(), components::anim_model(), components::cam()] {
+        if let Some(entity) = entity::get_component(player_id, entity_component) {
+            entity::despawn(entity);
+        }
+        entity::remove_component(player_id, entity_component);
+    }
+    entity::remove_component(player_id, components::role());
+    entity::remove_component(player_id, components::team());
+    entity::remove_component(player_id, components::target_pos());
+    entity::remove_component(player_id, components::hero_path());
+    entity::remove_component(player_id, components::hero_path_target());
+    entity::remove_component(player_id, components::hero_attack_target());
+    entity::remove_component(player_id, components::respawn_timer());
+    entity::remove_component(player_id, components::experience());
+    entity::remove_component(player_id, components::level());
+}
+
+fn share_experience(roster: &HeroRoster, unit: EntityId, killer: EntityId) {
+    let reward = entity::get_component(unit, components::experience_reward()).unwrap_or(0);
+    let (position, team) = match (
+        entity::get_component(unit, translation()),
+        entity::get_component(unit, components::team()),
+    ) {
+        (Some(position), Some(team)) => (position, team),
+        _ => return,
+    };
+    if reward == 0 {
+        return;
+    }
+    // neutral monsters are everyone's enemy, only the side that cleared them learns from it
+    let killer_team = entity::get_component(killer, components::team());
+    if team == NEUTRAL_TEAM && killer_team.is_none() {
+        return;
+    }
+
+    let players_around: Vec<EntityId> = query((player(), components::hero_model(), components::team()))
+        .build()
+        .evaluate()
+        .into_iter()
+        .filter(|(_, (_, model, hero_team))| {
+            *hero_team != team
+                && (team != NEUTRAL_TEAM || Some(*hero_team) == killer_team)
+                && is_alive(*model)
+                && entity::get_component(*model, translation()).map_or(false, |hero_position| {
+                    (hero_position.xy() - position.xy()).length() <= EXPERIENCE_RADIUS
+                })
+        })
+        .map(|(player_id, _)| player_id)
+        .collect();
+    if players_around.is_empty() {
+        return;
+    }
+
+    let share = reward / players_around.len() as u32;
+    for player_id in players_around {
+        gain_experience(roster, player_id, share);
+    }
+}
+
+fn gain_experience(roster: &HeroRoster, player_id: EntityId, amount: u32) {
+    let experience = entity::get_component(player_id, components::experience()).unwrap_or(0) + amount;
+    entity::set_component(player_id, components::experience(), experience);
+
+    let level = entity::get_component(player_id, components::level()).unwrap_or(1);
+    let new_level = roster.level_for(experience);
+    if new_level <= level {
+        return;
+    }
+
+    let role = entity::get_component(player_id, components::role()).unwrap();
+    let hero = roster.hero(role).unwrap();
+    let model = entity::get_component(player_id, components::hero_model()).unwrap();
+
+    // the health gained is added on top of the current health, not just to the maximum,
+    // and on top of what the items give
+    let health_gained = hero.health_at(new_level) - hero.health_at(level);
+    let max_health = entity::get_component(model, components::max_health()).unwrap();
+    let health = entity::get_component(model, components::health()).unwrap();
+    entity::set_component(model, components::max_health(), max_health + health_gained);
+    entity::set_component(model, components::health(), health + health_gained);
+
+    entity::set_component(player_id, components::level(), new_level);
+    messages::HeroLevelUp::new(player_id, new_level).send_client_broadcast_reliable();
+}
+
+fn hero_movement_system(map: MapData, hero_radius: f32, hero_animations: Vec<HeroAnimationPlayers>) {
+    // the towers are only blocked once they are spawned, see below
+    let mut standing_towers: Vec<Vec2> = Vec::new();
+    let mut walk_grid = WalkGrid::with_towers(&map, PATH_GRID_CELL_SIZE, hero_radius, &standing_towers);
+    let tower_query = query((components::is_tower(), translation())).build();
+
+    query((player(), components::hero_model(), components::role())).each_frame(move |list| {
+        // heroes walk where a tower fell, and around it again once the match is reset
+        let mut towers: Vec<Vec2> = tower_query
+            .evaluate()
+            .into_iter()
+            .map(|(_, (_, position))| position.xy())
+            .collect();
+        towers.sort_by(|first, second| first.x.total_cmp(&second.x).then(first.y.total_cmp(&second.y)));
+        if towers != standing_towers {
+            walk_grid = WalkGrid::with_towers(&map, PATH_GRID_CELL_SIZE, hero_radius, &towers);
+            standing_towers = towers;
+        }
+
+        for (player_id, (_, model, role)) in list {
+            if entity::has_component(model, components::is_dead()) {
+                continue;
+            }
+            let animations = hero_animations[role as usize];
+            let anim_model = entity::get_component(player_id, components::anim_model()).unwrap();
+            let anim_state = entity::get_component(anim_model, components::anim_state()).unwrap();
+
+            let attack_cooldown = entity::get_component(model, components::attack_cooldown()).unwrap();
+            if attack_cooldown > 0.0 {
+                entity::set_component(
+                    model,
+                    components::attack_cooldown(),
+                    attack_cooldown - delta_time(),
+                );
+            }
+
+            if anim_state == attack_animation_state!() {
+                continue;
+            }
+            // a stunned hero stands still, its orders wait for the stun to wear off
+            if entity::has_component(model, components::is_stunned()) {
+                if anim_state != idle_animation_state!() {
+                    entity::set_component(anim_model, apply_animation_player(), animations.idle.0);
+                    entity::set_component(
+                        anim_model,
+                        components::anim_state(),
+                        idle_animation_state!(),
+                    );
+                }
+                continue;
+            }
+            let current_pos = entity::get_component(model, translation()).unwrap();
+
+            if let Some(attack_target) =
+                entity::get_component(player_id, components::hero_attack_target())
+            {
+                if !is_alive(attack_target) {
+                    entity::remove_component(player_id, components::hero_attack_target());
+                    entity::set_component(player_id, components::target_pos(), current_pos.xy());
+                } else {
+                    let attack_target_pos =
+                        entity::get_component(attack_target, translation()).unwrap().xy();
+                    if (attack_target_pos - current_pos.xy()).length() <= stat(model, Stat::AttackRange) {
+                        // in range: stop walking and face the target
+                        entity::set_component(player_id, components::target_pos(), current_pos.xy());
+                        entity::set_component(
+                            model,
+                            rotation(),
+                            rotation_towards(attack_target_pos - current_pos.xy()),
+                        );
+                        if attack_cooldown <= 0.0 {
+                            start_hero_attack(player_id, model, anim_model, attack_target, animations);
+                            continue;
+                        }
+                    } else {
+                        entity::set_component(player_id, components::target_pos(), attack_target_pos);
+                    }
+                }
+            }
+
+            let target_pos = entity::get_component(player_id, components::target_pos()).unwrap();
+            let diff = target_pos - current_pos.xy();
+
+            // the path may end short of target_pos when it is inside a tower or a wall, or walled off
+            let next_point = match diff.length() < 1.0 {
+                true => None,
+                false => next_path_point(&walk_grid, player_id, current_pos.xy(), target_pos),
+            };
+
+            let Some(next_point) = next_point else {
+                entity::remove_component(player_id, components::hero_path());
+                entity::set_component(player_id, components::target_pos(), current_pos.xy());
+                // if current_pos.xy() == vec2(0.0, 0.0) {
+                physics::move_character(model, vec3(0., 0., -FALL_SPEED * delta_time()), 0.01, delta_time());
+                // }
+                if anim_state != idle_animation_state!() {
+                    entity::set_component(anim_model, apply_animation_player(), animations.idle.0);
+                    entity::set_component(
+                        anim_model,
+                        components::anim_state(),
+                        idle_animation_state!(),
+                    );
+                };
+                continue;
+            };
+
+            entity::set_component(model, rotation(), rotation_towards(next_point - current_pos.xy()));
+
+            // level, items, buffs and slows are already in there, see the stats module
+            let speed = stat(model, Stat::MoveSpeed);
+            let displace = step_towards(current_pos.xy(), next_point, speed, delta_time());
+
+            if anim_state != walk_animation_state!() {
+                entity::set_component(anim_model, apply_animation_player(), animations.walk.0);
+                entity::set_component(
+                    anim_model,
+                    components::anim_state(),
+                    walk_animation_state!(),
+                );
+            }
+            let collision = physics::move_character(
+                model,
+                vec3(displace.x, displace.y, -FALL_SPEED * delta_time()),
+                0.01,
+                delta_time(),
+            );
+
+            if collision.side {
+                // something is in the way, look for another way from here on the next frame
+                entity::remove_component(player_id, components::hero_path());
+            } else {
+                // messages::PlayerWalk::new(model, cam, player_id)
+                // .send_client_broadcast_unreliable();
+            }
+        }
+    });
+}
+
+// the point the hero walks to next, planning the path to target_pos again if needed.
+// None once the end of the path is reached
+fn next_path_point(
+    walk_grid: &WalkGrid,
+    player_id: EntityId,
+    position: Vec2,
+    target_pos: Vec2,
+) -> Option<Vec2> {
+    let planned_path = entity::get_component(player_id, components::hero_path()).filter(|_| {
+        entity::get_component(player_id, components::hero_path_target())
+            .map_or(false, |planned_for| planned_for.distance(target_pos) < PATH_REPLAN_DISTANCE)
+    });
+    let mut path = match planned_path {
+        Some(path) => path,
+        None => {
+            let path = walk_grid.find_path(position, target_pos)?;
+            entity::add_component(player_id, components::hero_path_target(), target_pos);
+            path
+        }
+    };
+
+    while path
+        .first()
+        .map_or(false, |point| point.distance(position) < PATH_POINT_REACHED_DISTANCE)
+    {
+        path.remove(0);
+    }
+    let next_point = path.first().copied();
+    entity::add_component(player_id, components::hero_path(), path);
+    next_point
+}
+
+fn start_hero_attack(
+    player_id: EntityId,
+    model: EntityId,
+    anim_model: EntityId,
+    attack_target: EntityId,
+    animations: HeroAnimationPlayers,
+) {
+    let attack_range = stat(model, Stat::AttackRange);
+    let attack_damage = stat(model, Stat::AttackDamage).round() as i32;
+
+    entity::set_component(model, components::attack_cooldown(), stat(model, Stat::AttackInterval));
+    entity::set_component(anim_model, apply_animation_player(), animations.attack.0);
+    entity::set_component(anim_model, components::anim_state(), attack_animation_state!());
+
+    run_async(async move {
+        // the damage lands when the torch hits, not when the swing starts
+        sleep(HERO_ATTACK_HIT_TIME).await;
+        // a hero killed or stunned during the swing doesn't land it, nor does it on a target that became invulnerable
+        let can_hit = is_alive(model)
+            && !entity::has_component(model, components::is_stunned())
+            && is_targetable(attack_target);
+        if can_hit {
+            let hero_pos = entity::get_component(model, translation()).unwrap().xy();
+            let target_pos = entity::get_component(attack_target, translation()).unwrap().xy();
+            if (target_pos - hero_pos).length() <= attack_range + HERO_ATTACK_RANGE_TOLERANCE {
+                deal_damage(attack_target, attack_damage, model);
+                messages::PlayerAttack::new(model, attack_target, player_id)
+                    .send_client_broadcast_unreliable();
+            }
+        }
+
+        sleep(HERO_ATTACK_ANIMATION_DURATION - HERO_ATTACK_HIT_TIME).await;
+        // the hero may have died during the swing, don't overwrite the death animation
+        if entity::get_component(anim_model, components::anim_state())
+            == Some(attack_animation_state!())
+        {
+            entity::set_component(anim_model, apply_animation_player(), animations.idle.0);
+            entity::set_component(anim_model, components::anim_state(), idle_animation_state!());
+        }
+    });
+}
+
+fn hero_death_system(hero_animations: Vec<HeroAnimationPlayers>) {
+    query((player(), components::hero_model(), components::role())).each_frame(move |list| {
+        for (player_id, (_, model, role)) in list {
+            if entity::has_component(model, components::is_dead()) {
+                continue;
+            }
+            let health = match entity::get_component(model, components::health()) {
+                Some(health) => health,
+                None => continue,
+            };
+            if health > 0 {
+                continue;
+            }
+
+            entity::add_component(model, components::is_dead(), ());
+
+            let killer = entity::get_component(model, components::last_attacker())
+                .unwrap_or(EntityId::null());
+            let bounty = entity::get_component(model, components::bounty()).unwrap_or(0);
+            messages::UnitKilled::new(model, killer, bounty).send_local_broadcast(false);
+
+            let anim_model = entity::get_component(player_id, components::anim_model()).unwrap();
+            entity::set_component(
+                anim_model,
+                apply_animation_player(),
+                hero_animations[role as usize].death.0,
+            );
+            entity::set_component(anim_model, components::anim_state(), death_animation_state!());
+
+            // the model, its anim_model and the camera are kept around and reused on respawn
+            let match_time = entity::get_component(entity::resources(), components::match_time())
+                .unwrap_or(0.0);
+            entity::add_component(player_id, components::respawn_timer(), respawn_time(match_time));
+        }
+    });
+}
+
+fn hero_respawn_system(map: MapData, hero_animations: Vec<HeroAnimationPlayers>) {
+    query((player(), components::respawn_timer())).each_frame(move |list| {
+        for (player_id, (_, respawn_timer)) in list {
+            if respawn_timer > 0.0 {
+                entity::set_component(
+                    player_id,
+                    components::respawn_timer(),
+                    respawn_timer - delta_time(),
+                );
+                continue;
+            }
+            entity::remove_component(player_id, components::respawn_timer());
+
+            let role = entity::get_component(player_id, components::role()).unwrap();
+            let team = entity::get_component(player_id, components::team()).unwrap();
+            let model = entity::get_component(player_id, components::hero_model()).unwrap();
+            let anim_model = entity::get_component(player_id, components::anim_model()).unwrap();
+            let cam = entity::get_component(player_id, components::cam()).unwrap();
+
+            let minus = camera_side(team);
+            let init_pos = spawn_position(&map, team);
+
+            entity::set_component(model, translation(), vec3(init_pos.x, init_pos.y, 3.0));
+            let max_health = entity::get_component(model, components::max_health()).unwrap();
+            entity::set_component(model, components::health(), max_health);
+            entity::remove_component(model, components::is_dead());
+            // whoever killed the hero last time gets nothing for its next death
+            entity::remove_component(model, components::last_attacker());
+
+            entity::set_component(
+                anim_model,
+                apply_animation_player(),
+                hero_animations[role as usize].idle.0,
+            );
+            entity::set_component(anim_model, components::anim_state(), idle_animation_state!());
+
+            entity::set_component(player_id, components::target_pos(), init_pos);
+
+            entity::set_component(
+                cam,
+                translation(),
+                vec3(init_pos.x + 10.0 * minus, init_pos.y, 15.0),
+            );
+            entity::set_component(cam, lookat_target(), vec3(init_pos.x, init_pos.y, 0.));
+        }
+    });
+}
+
+fn rotation_towards(direction: Vec2) -> Quat {
+    let initial_direction: Vec2 = Vec2::new(1.0, 0.0);
+    let dot = initial_direction.dot(direction);
+    let det = initial_direction.x * direction.y - initial_direction.y * direction.x;
+    let angle = det.atan2(dot);
+    Quat::from_rotation_z(angle - INIT_POS)
+}
+
+fn respawn_time(match_time: f32) -> f32 {
+    let minutes = match_time / 60.0;
+    (HERO_BASE_RESPAWN_TIME + minutes * HERO_RESPAWN_TIME_PER_MINUTE).min(HERO_MAX_RESPAWN_TIME)
+}
+
+/// Heroes appear next to their team's base, spread a bit towards the middle of the map.
+fn spawn_position(map: &MapData, team: u32) -> Vec2 {
+    let home = map.base_of_team(team).unwrap().position;
+    home - home.signum() * random::<Vec2>() * 3.0
+}
+
+fn camera_side(team: u32) -> f32 {
+    match team {
+        0 => 1.0,
+        _ => -1.0,
+    }
 }
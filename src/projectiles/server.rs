@@ -0,0 +1,177 @@
+use ambient_api::{
+    components::core::{
+        primitives::sphere_radius,
+        rendering::color,
+        transform::{scale, translation},
+    },
+    concepts::make_sphere,
+    prelude::*,
+};
+use amoba::{map_data::MapData, movement::fly_towards, spatial::SpatialGrid};
+
+#[path = "../server_common.rs"]
+mod server_common;
+use server_common::{deal_damage, is_alive, is_enemy};
+
+const PROJECTILE_RADIUS: f32 = 0.15;
+
+// Homing projectiles aim at the chest of their target instead of its feet
+const PROJECTILE_TARGET_HEIGHT: f32 = 1.0;
+
+// Projectiles flying to a point hit every enemy this close to it when they land
+const PROJECTILE_SPLASH_RADIUS: f32 = 1.5;
+
+// Side of the cells of the grid skillshots and splashes look for their victims in
+const PROJECTILE_GRID_CELL_SIZE: f32 = 2.5;
+
+const MARS_TEAM: u32 = 0;
+const JUPYTER_TEAM: u32 = 1;
+
+#[main]
+pub fn main() {
+    // Creeps, towers and abilities live in other modules, they ask for projectiles with this message
+    messages::SpawnProjectile::subscribe(|_, msg| {
+        spawn_projectile(msg);
+    });
+
+    messages::SpawnSkillshot::subscribe(|_, msg| {
+        let projectile = spawn_projectile(messages::SpawnProjectile::new(
+            msg.owner,
+            EntityId::null(),
+            msg.origin,
+            msg.destination,
+            msg.speed,
+            msg.damage,
+            msg.team,
+        ));
+        entity::add_component(projectile, components::skillshot_hit_radius(), msg.hit_radius);
+        if msg.status_duration > 0.0 {
+            entity::add_component(projectile, components::skillshot_status_kind(), msg.status_kind);
+            entity::add_component(
+                projectile,
+                components::skillshot_status_magnitude(),
+                msg.status_magnitude,
+            );
+            entity::add_component(
+                projectile,
+                components::skillshot_status_duration(),
+                msg.status_duration,
+            );
+        }
+    });
+
+    projectile_flight_system(MapData::load().ground_size);
+
+    messages::MatchReset::subscribe(|_, _| {
+        for (projectile, _) in query(components::is_projectile()).build().evaluate() {
+            entity::despawn(projectile);
+        }
+    });
+}
+
+fn spawn_projectile(msg: messages::SpawnProjectile) -> EntityId {
+    let projectile = Entity::new()
+        .with_merge(make_sphere())
+        .with(sphere_radius(), 1.)
+        .with(scale(), Vec3::ONE * PROJECTILE_RADIUS)
+        .with(translation(), msg.origin)
+        .with(color(), team_color(msg.team))
+        .with_default(components::is_projectile())
+        .with(components::projectile_owner(), msg.owner)
+        .with(components::projectile_destination(), msg.destination)
+        .with(components::projectile_speed(), msg.speed)
+        .with(components::projectile_damage(), msg.damage)
+        .with(components::team(), msg.team)
+        .spawn();
+
+    if !msg.target.is_null() {
+        entity::add_component(projectile, components::projectile_target(), msg.target);
+    }
+
+    projectile
+}
+
+fn projectile_flight_system(ground_size: f32) {
+    let damageable_query = query((components::health(), translation())).build();
+
+    query((components::is_projectile(), translation(), components::projectile_speed()))
+        .each_frame(move |list| {
+            // Everything that can be hit, by where it stands, so each skillshot and splash only looks around itself
+            let mut damageable = SpatialGrid::new(ground_size, PROJECTILE_GRID_CELL_SIZE);
+            damageable.extend(damageable_query.evaluate().into_iter().map(|(unit, (_, unit_position))| (unit_position.xy(), unit)));
+
+            for (projectile, (_, position, speed)) in list {
+                let target = entity::get_component(projectile, components::projectile_target());
+
+                let destination = match target {
+                    Some(target) => {
+                        // The target died or despawned while the projectile was flying, it just fizzles out
+                        if !is_alive(target) {
+                            entity::despawn(projectile);
+                            continue;
+                        }
+                        let target_position = entity::get_component(target, translation()).unwrap();
+                        target_position + Vec3::Z * PROJECTILE_TARGET_HEIGHT
+                    }
+                    None => entity::get_component(projectile, components::projectile_destination())
+                        .unwrap(),
+                };
+
+                let damage = entity::get_component(projectile, components::projectile_damage()).unwrap();
+                let owner = entity::get_component(projectile, components::projectile_owner()).unwrap();
+
+                //Skillshots stop at the first enemy on their way
+                if let Some(hit_radius) = entity::get_component(projectile, components::skillshot_hit_radius()) {
+                    let projectile_team = entity::get_component(projectile, components::team()).unwrap();
+                    let hit = damageable.nearest(position.xy(), hit_radius, |unit| {
+                        is_alive(*unit) && is_enemy(projectile_team, *unit)
+                    });
+                    if let Some((_, &unit)) = hit {
+                        deal_damage(unit, damage, owner);
+                        if let Some(kind) = entity::get_component(projectile, components::skillshot_status_kind()) {
+                            messages::ApplyStatusEffect::new(
+                                unit,
+                                kind,
+                                entity::get_component(projectile, components::skillshot_status_magnitude()).unwrap(),
+                                entity::get_component(projectile, components::skillshot_status_duration()).unwrap(),
+                                owner,
+                            )
+                            .send_local_broadcast(false);
+                        }
+                        entity::despawn(projectile);
+                        continue;
+                    }
+                }
+
+                if let Some(position) = fly_towards(position, destination, speed, delta_time()) {
+                    entity::set_component(projectile, translation(), position);
+                    continue;
+                }
+
+                match target {
+                    Some(target) => deal_damage(target, damage, owner),
+                    // a skillshot that reached its destination missed
+                    None if entity::has_component(projectile, components::skillshot_hit_radius()) => {}
+                    None => {
+                        let projectile_team = entity::get_component(projectile, components::team()).unwrap();
+
+                        for (_, &unit) in damageable.within(destination.xy(), PROJECTILE_SPLASH_RADIUS) {
+                            if is_alive(unit) && is_enemy(projectile_team, unit) {
+                                deal_damage(unit, damage, owner);
+                            }
+                        }
+                    }
+                }
+
+                entity::despawn(projectile);
+            }
+        });
+}
+
+fn team_color(team: u32) -> Vec4 {
+    match team {
+        MARS_TEAM => vec4(1.0, 0.0, 0.1, 1.),
+        JUPYTER_TEAM => vec4(0., 0., 1., 1.),
+        _ => vec4(1., 1., 1., 1.),
+    }
+}
@@ -0,0 +1,242 @@
+//! The hero roster, loaded from `data/heroes.toml`.
+//!
+//! The role a player picks is the index of its hero in the `heroes` list, so the
+//! team selection screen and the player module always agree on what a role means.
+
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::{
+    stats::{Stat, StatBlock},
+    status_effects::StatusDefinition,
+};
+
+const HEROES: &str = include_str!("../data/heroes.toml");
+
+/// A hero has at most one ability per key: Q, W, E and R.
+pub const MAX_ABILITIES: usize = 4;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HeroRoster {
+    /// Team names, indexed by the `team` component. 0 => Mars, 1 => Jupiter.
+    pub teams: Vec<String>,
+    /// Total experience needed to reach level 2, 3, and so on. Heroes start at level 1.
+    #[serde(default)]
+    pub level_thresholds: Vec<u32>,
+    pub heroes: Vec<HeroDefinition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HeroDefinition {
+    pub name: String,
+    pub team: u32,
+    /// Prefab url of the model, e.g. `assets/model/X Bot.fbx`.
+    pub model: String,
+    pub animations: HeroAnimations,
+    /// Units per second.
+    pub speed: f32,
+    pub health: i32,
+    pub attack_range: f32,
+    pub attack_damage: i32,
+    /// Seconds between two basic attacks.
+    pub attack_cooldown: f32,
+    /// Radius of the hero's character controller.
+    pub radius: f32,
+    pub mana: f32,
+    /// Mana regained per second.
+    pub mana_regen: f32,
+    /// Gold earned by the player who kills this hero.
+    pub bounty: u32,
+    /// Experience shared by the enemy heroes around when this hero dies.
+    pub experience: u32,
+    /// Stats gained on each level up.
+    #[serde(default)]
+    pub growth: HeroGrowth,
+    /// Cast with Q, W, E and R, in this order.
+    #[serde(default)]
+    pub abilities: Vec<AbilityDefinition>,
+}
+
+/// Animation clip urls of a hero.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HeroAnimations {
+    pub idle: String,
+    pub walk: String,
+    pub attack: String,
+    pub death: String,
+}
+
+/// What a hero gains per level above 1.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HeroGrowth {
+    #[serde(default)]
+    pub health: i32,
+    #[serde(default)]
+    pub attack_damage: i32,
+    #[serde(default)]
+    pub speed: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AbilityDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub targeting: AbilityTargeting,
+    #[serde(default)]
+    pub mana_cost: f32,
+    /// Seconds before the ability can be cast again.
+    pub cooldown: f32,
+    /// How far from the hero the target or the targeted point can be, and how far a skillshot flies.
+    #[serde(default)]
+    pub range: f32,
+    #[serde(default)]
+    pub damage: i32,
+    /// Radius of an area ability, or how close a skillshot must pass to hit.
+    #[serde(default)]
+    pub radius: f32,
+    /// Units per second of a skillshot.
+    #[serde(default)]
+    pub projectile_speed: f32,
+    /// Health given back by a self buff.
+    #[serde(default)]
+    pub heal: i32,
+    /// Speed added by a self buff for `duration` seconds, as a speed boost status effect.
+    #[serde(default)]
+    pub speed_bonus: f32,
+    #[serde(default)]
+    pub duration: f32,
+    /// Applied to every unit the ability hits, or to the hero itself for a self buff.
+    #[serde(default)]
+    pub status: Option<StatusDefinition>,
+}
+
+/// What an ability is aimed at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AbilityTargeting {
+    /// An enemy unit under the cursor.
+    Unit,
+    /// Every enemy around the point of the ground under the cursor.
+    Area,
+    /// The hero itself.
+    SelfBuff,
+    /// A projectile flying towards the cursor, hitting the first enemy on its way.
+    Skillshot,
+}
+
+#[derive(Debug)]
+pub enum RosterError {
+    Toml(toml::de::Error),
+    NoHeroes,
+    UnknownTeam { hero: String, team: u32 },
+    UnsortedLevels,
+    TooManyAbilities { hero: String },
+}
+
+impl fmt::Display for RosterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RosterError::Toml(error) => write!(f, "invalid hero roster: {error}"),
+            RosterError::NoHeroes => write!(f, "the hero roster has no heroes"),
+            RosterError::UnknownTeam { hero, team } => {
+                write!(f, "hero {hero:?} belongs to team {team}, which is not in `teams`")
+            }
+            RosterError::UnsortedLevels => {
+                write!(f, "`level_thresholds` must go up from one level to the next")
+            }
+            RosterError::TooManyAbilities { hero } => {
+                write!(f, "hero {hero:?} has more than {MAX_ABILITIES} abilities")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RosterError {}
+
+impl HeroRoster {
+    /// The roster shipped in `data/heroes.toml`. Panics if it is broken, as the game can't run without heroes.
+    pub fn load() -> Self {
+        match Self::parse(HEROES) {
+            Ok(roster) => roster,
+            Err(error) => panic!("data/heroes.toml: {error}"),
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self, RosterError> {
+        let roster: HeroRoster = toml::from_str(source).map_err(RosterError::Toml)?;
+        roster.validate()?;
+        Ok(roster)
+    }
+
+    pub fn hero(&self, role: u32) -> Option<&HeroDefinition> {
+        self.heroes.get(role as usize)
+    }
+
+    /// All heroes of a team, along with the role to send to pick them.
+    pub fn heroes_of_team(&self, team: u32) -> impl Iterator<Item = (u32, &HeroDefinition)> {
+        self.heroes
+            .iter()
+            .enumerate()
+            .filter(move |(_, hero)| hero.team == team)
+            .map(|(role, hero)| (role as u32, hero))
+    }
+
+    /// Level of a hero with `experience` points, starting at 1.
+    pub fn level_for(&self, experience: u32) -> u32 {
+        let reached = self
+            .level_thresholds
+            .iter()
+            .take_while(|threshold| experience >= **threshold)
+            .count();
+        1 + reached as u32
+    }
+
+    fn validate(&self) -> Result<(), RosterError> {
+        if self.heroes.is_empty() {
+            return Err(RosterError::NoHeroes);
+        }
+        if self.level_thresholds.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(RosterError::UnsortedLevels);
+        }
+        for hero in &self.heroes {
+            if hero.team as usize >= self.teams.len() {
+                return Err(RosterError::UnknownTeam {
+                    hero: hero.name.clone(),
+                    team: hero.team,
+                });
+            }
+            if hero.abilities.len() > MAX_ABILITIES {
+                return Err(RosterError::TooManyAbilities {
+                    hero: hero.name.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HeroDefinition {
+    pub fn health_at(&self, level: u32) -> i32 {
+        self.health + self.growth.health * (level as i32 - 1)
+    }
+
+    pub fn attack_damage_at(&self, level: u32) -> i32 {
+        self.attack_damage + self.growth.attack_damage * (level as i32 - 1)
+    }
+
+    pub fn speed_at(&self, level: u32) -> f32 {
+        self.speed + self.growth.speed * (level - 1) as f32
+    }
+
+    /// Stats of the hero at level 1, without items.
+    pub fn base_stats(&self) -> StatBlock {
+        let mut stats = StatBlock::ZERO;
+        stats[Stat::MoveSpeed] = self.speed;
+        stats[Stat::AttackDamage] = self.attack_damage as f32;
+        stats[Stat::AttackRange] = self.attack_range;
+        stats[Stat::AttackInterval] = self.attack_cooldown;
+        stats
+    }
+}
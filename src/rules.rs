@@ -0,0 +1,72 @@
+//! Rules of the fights on the lanes, shared by the server modules and the headless
+//! [`Simulation`](crate::simulation::Simulation), so what a test of one shows holds for the other.
+//!
+//! Everything here works on plain values: the servers read them from their entities, the
+//! simulation from its own units.
+
+use glam::Vec2;
+
+use crate::{creeps::TargetKind, map_data::NEUTRAL_TEAM};
+
+/// Whether a creep or a tower of `team` goes after a unit of `other` on its own. Neutral
+/// monsters are left alone, they only fight whoever pulls them out of their camp.
+pub fn is_lane_enemy(team: u32, other: u32) -> bool {
+    other != team && other != NEUTRAL_TEAM
+}
+
+/// What a creep walking its lane goes after: the closest enemy `closest` finds of the first
+/// kind of `priority` that has one.
+pub fn pick_target<T>(
+    priority: &[TargetKind],
+    closest: impl FnMut(&TargetKind) -> Option<T>,
+) -> Option<T> {
+    priority.iter().find_map(closest)
+}
+
+/// Whether a creep at `position` chased something too far from `leash_origin`, where it left
+/// its lane or camp, and has to walk back.
+pub fn is_leashed(leash_origin: Vec2, position: Vec2, leash_distance: f32) -> bool {
+    position.distance(leash_origin) > leash_distance
+}
+
+/// A frame of `delta_time` seconds of a unit in range of its target, with `cooldown` seconds
+/// left before it can attack. Returns whether it attacks this frame, and its cooldown afterwards.
+pub fn attack_step(cooldown: f32, delta_time: f32, interval: f32) -> (bool, f32) {
+    match cooldown > 0. {
+        true => (false, cooldown - delta_time),
+        false => (true, interval),
+    }
+}
+
+/// A tower still standing, as far as protecting the ones behind it goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TowerSlot {
+    pub team: u32,
+    /// Index of its lane in the map.
+    pub lane: u32,
+    /// 0 for the outermost tower of the lane.
+    pub tier: u32,
+}
+
+/// A tower can't be hit while a tower in front of it on its lane stands. `standing` is every
+/// tower still up.
+pub fn is_tower_protected(tower: TowerSlot, standing: &[TowerSlot]) -> bool {
+    standing.iter().any(|other| {
+        other.team == tower.team && other.lane == tower.lane && other.tier < tower.tier
+    })
+}
+
+/// A base can't be hit while each lane of its team that had towers still has one.
+/// `defended_lanes` is the (team, lane) of every lane that started with towers.
+pub fn is_base_protected(team: u32, defended_lanes: &[(u32, u32)], standing: &[TowerSlot]) -> bool {
+    let mut lanes_of_base = defended_lanes
+        .iter()
+        .filter(|(lane_team, _)| *lane_team == team)
+        .peekable();
+    lanes_of_base.peek().is_some()
+        && lanes_of_base.all(|(lane_team, lane)| {
+            standing
+                .iter()
+                .any(|tower| tower.team == *lane_team && tower.lane == *lane)
+        })
+}
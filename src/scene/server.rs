@@ -1,11 +1,12 @@
 use ambient_api::{
     components::core::{
-        app::main_scene,
+        app::{main_scene, name},
         camera::aspect_ratio_from_window,
         physics::{
             angular_velocity, cube_collider, dynamic, linear_velocity, physics_controlled,
             plane_collider, sphere_collider,
         },
+        player::player,
         primitives::{cube, quad, sphere_radius},
         rendering::{cast_shadows, color, fog_density, light_diffuse, sky, sun, water},
         transform::{lookat_target, rotation, scale, translation},
@@ -13,90 +14,128 @@ use ambient_api::{
     concepts::{make_perspective_infinite_reverse_camera, make_sphere, make_transformable},
     prelude::*,
 };
+use amoba::{
+    map_data::{BaseDefinition, LaneDefinition, MapData, ObstacleDefinition, BASE_RADIUS},
+    match_state::{MatchState, NO_WINNER},
+};
 
 const MARS_TEAM: u32 = 0;
 const JUPYTER_TEAM: u32 = 1;
 
+// obstacles are walls, heroes can't see over them anyway
+const OBSTACLE_HEIGHT: f32 = 2.0;
+
+// seconds the victory screen stays up before everything is reset for the next match
+const MATCH_RESET_DELAY: f32 = 10.0;
+
 #[main]
 pub fn main() {
-    let mars_ball = Entity::new()
-        .with_merge(make_sphere())
-        .with_default(cast_shadows())
-        .with(sphere_radius(), 1.)
-        .with(sphere_collider(), 1.0)
-        .with(translation(), vec3(15., 15., 1.))
-        .with(color(), vec4(1.0, 0.0, 0.1, 1.))
-        .with(components::health(), 100)
-        .with(components::base_side(), 0)
-        .spawn();
+    let map = MapData::load();
+    for issue in map.validate() {
+        println!("map warning: {}", issue);
+    }
+
+    for base in &map.bases {
+        spawn_base(base);
+    }
+
+    for (lane_index, lane) in map.lanes.iter().enumerate() {
+        spawn_lane_path(&map, lane_index as u32, lane);
+    }
+
+    entity::add_component(entity::resources(), components::match_time(), 0.);
+    entity::add_component(
+        entity::resources(),
+        components::match_state(),
+        MatchState::Lobby as u32,
+    );
+
+    spawn_query(player()).bind(|_| {
+        if match_state() == MatchState::Lobby {
+            set_match_state(MatchState::PickPhase);
+        }
+    });
 
-    let jupyter_ball = Entity::new()
-        .with_merge(make_sphere())
-        .with_default(cast_shadows())
-        .with(sphere_collider(), 1.0)
-        .with(sphere_radius(), 1.)
-        .with(translation(), vec3(-15., -15., 1.))
-        .with(color(), vec4(0., 0., 1., 1.))
-        .with(components::health(), 100)
-        .with(components::base_side(), 1)
-        .spawn();
+    // the match starts as soon as someone picks a hero
+    messages::ChooseRole::subscribe(|_, _| {
+        if match_state() == MatchState::PickPhase {
+            set_match_state(MatchState::InProgress);
+        }
+    });
 
-    spawn_mars_spawn_points_and_paths();
-
-    spawn_jupyter_spawn_points_and_paths();
-
-    query((components::health(), components::base_side())).each_frame(|list| {
-        for (base_id, (health, side)) in list {
-            if health <= 0 {
-                println!("base {} destroyed", base_id);
-                // messages::BlowSound::new(base_id).send_client_broadcast_unreliable();
-                let c = if side == 0 {
-                    vec4(1.0, 0.0, 0.1, 1.)
-                } else {
-                    vec4(0., 0., 1., 1.)
-                };
-                let pos = entity::get_component(base_id, translation()).unwrap();
-                entity::despawn(base_id);
-                run_async(async move {
-                    // loop {
-
-                    for _ in 0..20 {
-                        // sleep(0.01).await;
-                        let pos =
-                            pos + vec3(random::<f32>(), random::<f32>(), random::<f32>()) * 0.3;
-                        let size = vec3(0.3, 0.3, 0.1);
-                        let rot = Quat::from_rotation_y(random::<f32>() * 3.14)
-                            * Quat::from_rotation_x(random::<f32>() * 3.14);
-                        let id = Entity::new()
-                            .with_merge(make_transformable())
-                            .with_default(cube())
-                            .with(rotation(), rot)
-                            .with_default(physics_controlled())
-                            .with_default(cast_shadows())
-                            .with(linear_velocity(), vec3(random(), random(), 15.0))
-                            // .with(angular_velocity(), random::<Vec3>() * 1.0)
-                            .with(cube_collider(), Vec3::ONE)
-                            .with(dynamic(), true)
-                            .with(scale(), random::<Vec3>() * size * 2.0)
-                            .with(translation(), pos)
-                            .with(color(), c)
-                            .spawn();
-                    }
-                    // }
-                });
+    ambient_api::messages::Frame::subscribe(|_| {
+        if match_state() != MatchState::InProgress {
+            return;
+        }
+        let match_time = entity::get_component(entity::resources(), components::match_time()).unwrap();
+        entity::set_component(entity::resources(), components::match_time(), match_time + delta_time());
+    });
+
+    let bases_map = map.clone();
+    query((components::health(), components::base_side())).each_frame(move |list| {
+        let fallen: Vec<(EntityId, u32)> = list
+            .into_iter()
+            .filter(|(_, (health, _))| *health <= 0)
+            .map(|(base_id, (_, side))| (base_id, side))
+            .collect();
+
+        // bases falling in the same frame end the match with no winner, like in the simulation
+        if match_state() == MatchState::InProgress {
+            match fallen.as_slice() {
+                [] => {}
+                [(_, side)] => finish_match(bases_map.clone(), other_team(*side)),
+                _ => finish_match(bases_map.clone(), NO_WINNER),
             }
         }
+
+        for (base_id, side) in fallen {
+            println!("base {} destroyed", base_id);
+            // messages::BlowSound::new(base_id).send_client_broadcast_unreliable();
+            let c = team_color(side);
+            let pos = entity::get_component(base_id, translation()).unwrap();
+            entity::despawn(base_id);
+            run_async(async move {
+                // loop {
+
+                for _ in 0..20 {
+                    // sleep(0.01).await;
+                    let pos = pos + vec3(random::<f32>(), random::<f32>(), random::<f32>()) * 0.3;
+                    let size = vec3(0.3, 0.3, 0.1);
+                    let rot = Quat::from_rotation_y(random::<f32>() * 3.14)
+                        * Quat::from_rotation_x(random::<f32>() * 3.14);
+                    let id = Entity::new()
+                        .with_merge(make_transformable())
+                        .with_default(cube())
+                        .with(rotation(), rot)
+                        .with_default(physics_controlled())
+                        .with_default(cast_shadows())
+                        .with(linear_velocity(), vec3(random(), random(), 15.0))
+                        // .with(angular_velocity(), random::<Vec3>() * 1.0)
+                        .with(cube_collider(), Vec3::ONE)
+                        .with(dynamic(), true)
+                        .with(scale(), random::<Vec3>() * size * 2.0)
+                        .with(translation(), pos)
+                        .with(color(), c)
+                        .spawn();
+                }
+                // }
+            });
+        }
     });
 
     Entity::new()
         .with_merge(make_transformable())
         .with_default(quad())
-        .with(scale(), Vec3::ONE * 30.)
+        .with(scale(), Vec3::ONE * map.ground_size)
         .with_default(plane_collider())
         // .with(color(), vec4(1., 0., 0., 1.))
         .with(translation(), vec3(0., 0., 0.01))
         .spawn();
 
+    for obstacle in &map.obstacles {
+        spawn_obstacle(obstacle);
+    }
+
     Entity::new()
         .with_merge(make_transformable())
         .with_default(water())
@@ -127,141 +166,106 @@ pub fn main() {
         .spawn();
 }
 
-fn spawn_mars_spawn_points_and_paths(){
-    spawn_mars_left_spawn_path();
-
-    spawn_mars_middle_spawn_path();
-
-    spawn_mars_right_spawn_path();
+fn match_state() -> MatchState {
+    let state = entity::get_component(entity::resources(), components::match_state()).unwrap();
+    MatchState::from_u32(state).unwrap()
 }
 
-fn spawn_mars_left_spawn_path(){
-    let path_point_mars_left_spawn = Entity::new()
-        .with(translation(), vec3(13., 13., 1.))
-        .with(components::is_path_point(), true)
-        .with(components::is_creep_spawn_point(), MARS_TEAM)
-        .spawn();
-
-    let path_point_mars_left_middle_of_path = Entity::new()
-        .with(translation(), vec3(-14., 13., 1.))
-        .with(components::is_path_point(), true)
-        .spawn();
-
-    entity::add_component(path_point_mars_left_spawn, components::next_path_point(), path_point_mars_left_middle_of_path);
-
-    let path_point_mars_left_end_of_path = Entity::new()
-        .with(translation(), vec3(-14., -11., 1.))
-        .with(components::is_path_point(), true)
-        .spawn();
-
-    entity::add_component(path_point_mars_left_middle_of_path, components::next_path_point(), path_point_mars_left_end_of_path);
+fn set_match_state(state: MatchState) {
+    println!("match state: {:?}", state);
+    entity::set_component(entity::resources(), components::match_state(), state as u32);
 }
 
-fn spawn_mars_middle_spawn_path(){
-    let path_point_mars_middle_spawn = Entity::new()
-        .with(translation(), vec3(13.,13.,1.))
-        .with(components::is_path_point(), true)
-        .with(components::is_creep_spawn_point(), MARS_TEAM)
-        .spawn();
-
-    let path_point_mars_middle_end_of_path = Entity::new()
-        .with(translation(), vec3(-13., -13., 1.))
-        .with(components::is_path_point(), true)
-        .spawn();
-
-    entity::add_component(path_point_mars_middle_spawn, components::next_path_point(), path_point_mars_middle_end_of_path);
+fn finish_match(map: MapData, winner: u32) {
+    match winner {
+        NO_WINNER => println!("the match ended in a draw"),
+        _ => println!("team {} won the match", winner),
+    }
+    entity::add_component(entity::resources(), components::match_winner(), winner);
+    set_match_state(MatchState::Finished);
+    messages::MatchEnded::new(winner).send_client_broadcast_reliable();
+    messages::MatchEnded::new(winner).send_local_broadcast(false);
+
+    run_async(async move {
+        sleep(MATCH_RESET_DELAY).await;
+        reset_match(&map);
+    });
 }
 
-fn spawn_mars_right_spawn_path(){
-    let path_point_mars_right_spawn = Entity::new()
-        .with(translation(), vec3(13., 13., 1.))
-        .with(components::is_path_point(), true)
-        .with(components::is_creep_spawn_point(), MARS_TEAM)
-        .spawn();
-
-    let path_point_mars_right_middle_of_path = Entity::new()
-        .with(translation(), vec3(13., -14., 1.))
-        .with(components::is_path_point(), true)
-        .spawn();
-
-    entity::add_component(path_point_mars_right_spawn, components::next_path_point(), path_point_mars_right_middle_of_path);
-
-    let path_point_mars_right_end_of_path = Entity::new()
-        .with(translation(), vec3(-11., -14., 1.))
-        .with(components::is_path_point(), true)
-        .spawn();
-
-    entity::add_component(path_point_mars_right_middle_of_path, components::next_path_point(), path_point_mars_right_end_of_path);
-
+// every module clears its own units on MatchReset, the scene only rebuilds the bases and the clock
+fn reset_match(map: &MapData) {
+    for (base_id, _) in query(components::base_side()).build().evaluate() {
+        entity::despawn(base_id);
+    }
+    for base in &map.bases {
+        spawn_base(base);
+    }
+
+    entity::set_component(entity::resources(), components::match_time(), 0.);
+    entity::remove_component(entity::resources(), components::match_winner());
+    set_match_state(MatchState::PickPhase);
+
+    messages::MatchReset::new().send_local_broadcast(false);
+    messages::MatchReset::new().send_client_broadcast_reliable();
 }
 
-fn spawn_jupyter_spawn_points_and_paths(){
-    spawn_jupyter_left_spawn_path();
-
-    spawn_jupyter_middle_spawn_path();
-
-    spawn_jupyter_right_spawn_path();
+fn other_team(team: u32) -> u32 {
+    match team {
+        MARS_TEAM => JUPYTER_TEAM,
+        _ => MARS_TEAM,
+    }
 }
 
-fn spawn_jupyter_left_spawn_path(){
-
-    let path_point_jupyter_left_spawn = Entity::new()
-        .with(translation(), vec3(-14., -11., 1.))
-        .with(components::is_path_point(), true)
-        .with(components::is_creep_spawn_point(), JUPYTER_TEAM)
-        .spawn();
-
-    let path_point_jupyter_left_middle_of_path = Entity::new()
-        .with(translation(), vec3(-14., 13., 1.))
-        .with(components::is_path_point(), true)
-        .spawn();
-
-    entity::add_component(path_point_jupyter_left_spawn, components::next_path_point(), path_point_jupyter_left_middle_of_path);
-
-    let path_point_jupyter_left_end_of_path = Entity::new()
-        .with(translation(), vec3(13., 13., 1.))
-        .with(components::is_path_point(), true)
-        .spawn();
-
-    entity::add_component(path_point_jupyter_left_middle_of_path, components::next_path_point(), path_point_jupyter_left_end_of_path);
-
+fn spawn_base(base: &BaseDefinition) -> EntityId {
+    Entity::new()
+        .with_merge(make_sphere())
+        .with_default(cast_shadows())
+        .with(sphere_radius(), BASE_RADIUS)
+        .with(sphere_collider(), BASE_RADIUS)
+        .with(translation(), base.position.extend(1.))
+        .with(color(), team_color(base.team))
+        .with(components::health(), base.health)
+        .with(components::base_side(), base.team)
+        .spawn()
 }
 
-fn spawn_jupyter_middle_spawn_path(){
-    let path_point_jupyter_middle_spawn = Entity::new()
-        .with(translation(), vec3(-13., -13., 1.))
-        .with(components::is_path_point(), true)
-        .with(components::is_creep_spawn_point(), JUPYTER_TEAM)
-        .spawn();
-
-    let path_point_jupyter_middle_end_of_path = Entity::new()
-        .with(translation(), vec3(13.,13.,1.))
-        .with(components::is_path_point(), true)
-        .spawn();
-
-    entity::add_component(path_point_jupyter_middle_spawn, components::next_path_point(), path_point_jupyter_middle_end_of_path);
-
-    
+// heroes walk around them, see the pathfinding module
+fn spawn_obstacle(obstacle: &ObstacleDefinition) -> EntityId {
+    Entity::new()
+        .with_merge(make_transformable())
+        .with_default(cube())
+        .with_default(cast_shadows())
+        .with(cube_collider(), Vec3::ONE)
+        .with(translation(), obstacle.position.extend(OBSTACLE_HEIGHT / 2.))
+        .with(scale(), obstacle.size.extend(OBSTACLE_HEIGHT))
+        .with(color(), vec4(0.4, 0.35, 0.3, 1.))
+        .with(name(), obstacle.name.clone())
+        .spawn()
 }
 
-fn spawn_jupyter_right_spawn_path(){
-    let path_point_jupyter_right_spawn = Entity::new()
-    .with(translation(), vec3(-11., -14., 1.))
-    .with(components::is_path_point(), true)
-    .with(components::is_creep_spawn_point(), JUPYTER_TEAM)
-    .spawn();
+fn spawn_lane_path(map: &MapData, lane_index: u32, lane: &LaneDefinition) {
+    let team = map.lane_team(lane);
 
-    let path_point_jupyter_right_middle_of_path = Entity::new()
-        .with(translation(), vec3(13., -14., 1.))
-        .with(components::is_path_point(), true)
-        .spawn();
+    //Each lane gets its own chain of path points, even when lanes share waypoints, as a path point only knows one next_path_point
+    let path_points: Vec<EntityId> = map.lane_path(lane).iter().map(|position| {
+        Entity::new()
+            .with(translation(), position.extend(1.))
+            .with(components::is_path_point(), true)
+            .with(components::lane(), lane_index)
+            .spawn()
+    }).collect();
 
-    entity::add_component(path_point_jupyter_right_spawn, components::next_path_point(), path_point_jupyter_right_middle_of_path);
+    entity::add_component(path_points[0], components::is_creep_spawn_point(), team);
 
-    let path_point_jupyter_right_end_of_path = Entity::new()
-        .with(translation(), vec3(13., 13., 1.))
-        .with(components::is_path_point(), true)
-        .spawn();
+    for pair in path_points.windows(2) {
+        entity::add_component(pair[0], components::next_path_point(), pair[1]);
+    }
+}
 
-    entity::add_component(path_point_jupyter_right_middle_of_path, components::next_path_point(), path_point_jupyter_right_end_of_path);
-}
\ No newline at end of file
+fn team_color(team: u32) -> Vec4 {
+    match team {
+        MARS_TEAM => vec4(1.0, 0.0, 0.1, 1.),
+        JUPYTER_TEAM => vec4(0., 0., 1., 1.),
+        _ => vec4(1., 1., 1., 1.),
+    }
+}
@@ -0,0 +1,77 @@
+//! What every server module needs to know about a unit and how it gets hurt.
+//!
+//! It reads the components of the module it is compiled in, so it can't live in the `amoba`
+//! library: each server includes it with `#[path = "../server_common.rs"] mod server_common;`.
+//! Every damage, from an attack, a projectile, an ability or an effect over time, goes through
+//! [`deal_damage`], so invulnerability, shields and kill credit work the same for all of them.
+
+// not every module uses every helper
+#![allow(dead_code)]
+
+use ambient_api::prelude::*;
+use amoba::{
+    stats::{Stat, StatBlock},
+    status_effects::StatusEffects,
+};
+
+use crate::components;
+
+/// A unit is alive while it exists, still has health left and has not started dying.
+/// Every targeting decision should go through this, so nobody keeps chasing or hitting a corpse.
+pub fn is_alive(unit: EntityId) -> bool {
+    entity::exists(unit)
+        && !entity::has_component(unit, components::is_dead())
+        && entity::get_component(unit, components::health()).map_or(true, |health| health > 0)
+}
+
+/// Alive and not protected, like a tower standing behind another tower of its lane.
+pub fn is_targetable(unit: EntityId) -> bool {
+    is_alive(unit) && !entity::has_component(unit, components::is_invulnerable())
+}
+
+/// Whether a unit of `team` may hit `unit`: anything of another team, neutral monsters included.
+pub fn is_enemy(team: u32, unit: EntityId) -> bool {
+    // creeps and heroes have a team, bases only know their side
+    match entity::get_component(unit, components::team()) {
+        Some(unit_team) => unit_team != team,
+        None => {
+            entity::get_component(unit, components::base_side()).map_or(false, |side| side != team)
+        }
+    }
+}
+
+/// The stats of a unit with its items, buffs, slows and levels, kept up to date by the stats module.
+pub fn stat(unit: EntityId, stat: Stat) -> f32 {
+    entity::get_component(unit, components::stats())
+        .map_or(0.0, |stats| StatBlock::from_component(&stats)[stat])
+}
+
+/// Hurts `unit` for `damage`, crediting `attacker` if it dies of it. Dead and invulnerable units
+/// take nothing and shields soak up the damage before health does.
+pub fn deal_damage(unit: EntityId, damage: i32, attacker: EntityId) {
+    if damage <= 0 || !is_targetable(unit) {
+        return;
+    }
+    if let Some(health) = entity::get_component(unit, components::health()) {
+        let damage = absorb_with_shields(unit, damage);
+        entity::set_component(unit, components::health(), health - damage);
+        entity::add_component(unit, components::last_attacker(), attacker);
+    }
+}
+
+fn absorb_with_shields(unit: EntityId, damage: i32) -> i32 {
+    let (Some(kinds), Some(magnitudes), Some(durations), Some(next_ticks), Some(sources)) = (
+        entity::get_component(unit, components::status_kinds()),
+        entity::get_component(unit, components::status_magnitudes()),
+        entity::get_component(unit, components::status_durations()),
+        entity::get_component(unit, components::status_next_ticks()),
+        entity::get_component(unit, components::status_sources()),
+    ) else {
+        return damage;
+    };
+    let mut effects =
+        StatusEffects::from_components(&kinds, &magnitudes, &durations, &next_ticks, &sources);
+    let damage = effects.absorb(damage);
+    entity::set_component(unit, components::status_magnitudes(), effects.magnitudes());
+    damage
+}
@@ -0,0 +1,135 @@
+//! The item shop, loaded from `data/shop.toml`.
+//!
+//! Items are bought by their index in the `items` list, which is also what the
+//! `inventory` component of a player holds in each of its slots.
+
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::status_effects::StatusDefinition;
+
+const SHOP: &str = include_str!("../data/shop.toml");
+
+/// Value of an `inventory` slot with no item in it.
+pub const EMPTY_SLOT: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Shop {
+    /// Number of slots of the inventory of every player.
+    pub inventory_slots: usize,
+    /// Part of its cost an item sells back for, between 0 and 1.
+    pub sell_ratio: f32,
+    /// How close to its own base a hero must be to buy or sell.
+    pub shop_radius: f32,
+    pub items: Vec<ItemDefinition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ItemDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub cost: u32,
+    /// Stats added to the hero while the item is in its inventory.
+    #[serde(default)]
+    pub modifiers: StatModifiers,
+    /// What the item does when used. Items with an active effect are used up.
+    #[serde(default)]
+    pub active: Option<ActiveEffect>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub struct StatModifiers {
+    #[serde(default)]
+    pub health: i32,
+    #[serde(default)]
+    pub attack_damage: i32,
+    #[serde(default)]
+    pub speed: f32,
+}
+
+impl std::ops::Add for StatModifiers {
+    type Output = StatModifiers;
+
+    fn add(self, other: StatModifiers) -> StatModifiers {
+        StatModifiers {
+            health: self.health + other.health,
+            attack_damage: self.attack_damage + other.attack_damage,
+            speed: self.speed + other.speed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(tag = "effect", rename_all = "lowercase")]
+pub enum ActiveEffect {
+    /// Gives back `amount` health, up to the hero's maximum.
+    Heal { amount: i32 },
+    /// Puts a status effect on the hero, e.g. a shield.
+    Status(StatusDefinition),
+}
+
+#[derive(Debug)]
+pub enum ShopError {
+    Toml(toml::de::Error),
+    NoInventory,
+    InvalidSellRatio(f32),
+}
+
+impl fmt::Display for ShopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShopError::Toml(error) => write!(f, "invalid shop: {error}"),
+            ShopError::NoInventory => write!(f, "`inventory_slots` must be at least 1"),
+            ShopError::InvalidSellRatio(ratio) => {
+                write!(f, "`sell_ratio` is {ratio}, it must be between 0 and 1")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShopError {}
+
+impl Shop {
+    /// The shop shipped in `data/shop.toml`. Panics if it is broken.
+    pub fn load() -> Self {
+        match Self::parse(SHOP) {
+            Ok(shop) => shop,
+            Err(error) => panic!("data/shop.toml: {error}"),
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self, ShopError> {
+        let shop: Shop = toml::from_str(source).map_err(ShopError::Toml)?;
+        if shop.inventory_slots == 0 {
+            return Err(ShopError::NoInventory);
+        }
+        if !(0.0..=1.0).contains(&shop.sell_ratio) {
+            return Err(ShopError::InvalidSellRatio(shop.sell_ratio));
+        }
+        Ok(shop)
+    }
+
+    pub fn item(&self, item: u32) -> Option<&ItemDefinition> {
+        self.items.get(item as usize)
+    }
+
+    /// A fresh inventory, with every slot empty.
+    pub fn empty_inventory(&self) -> Vec<u32> {
+        vec![EMPTY_SLOT; self.inventory_slots]
+    }
+
+    /// Gold given back for selling an item.
+    pub fn sell_price(&self, item: &ItemDefinition) -> u32 {
+        (item.cost as f32 * self.sell_ratio) as u32
+    }
+
+    /// Sum of the modifiers of every item of an inventory.
+    pub fn modifiers(&self, inventory: &[u32]) -> StatModifiers {
+        inventory
+            .iter()
+            .filter_map(|item| self.item(*item))
+            .fold(StatModifiers::default(), |total, item| total + item.modifiers)
+    }
+}
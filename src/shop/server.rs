@@ -0,0 +1,212 @@
+use ambient_api::{
+    components::core::{player::player, transform::translation},
+    prelude::*,
+};
+use amoba::{
+    map_data::MapData,
+    shop::{ActiveEffect, Shop, StatModifiers, EMPTY_SLOT},
+};
+
+#[main]
+pub fn main() {
+    let shop = Shop::load();
+    let map = MapData::load();
+
+    // every hero starts with an empty inventory, including the ones picked after a match reset
+    spawn_query((player(), components::hero_model())).bind({
+        let shop = shop.clone();
+        move |players| {
+            for (player_id, _) in players {
+                entity::add_component(player_id, components::inventory(), shop.empty_inventory());
+            }
+        }
+    });
+
+    messages::BuyItem::subscribe({
+        let shop = shop.clone();
+        let map = map.clone();
+        move |source, msg| {
+            // a player id straight from a client can't be trusted, the ui module relays these with the sender's
+            if source.local().is_none() {
+                return;
+            }
+            buy_item(&shop, &map, msg.player_id, msg.item);
+        }
+    });
+
+    messages::SellItem::subscribe({
+        let shop = shop.clone();
+        let map = map.clone();
+        move |source, msg| {
+            if source.local().is_none() {
+                return;
+            }
+            sell_item(&shop, &map, msg.player_id, msg.slot);
+        }
+    });
+
+    messages::UseItem::subscribe({
+        let shop = shop.clone();
+        move |source, msg| {
+            if source.local().is_none() {
+                return;
+            }
+            use_item(&shop, msg.player_id, msg.slot);
+        }
+    });
+
+    messages::MatchReset::subscribe(|_, _| {
+        for (player_id, _) in query(components::inventory()).build().evaluate() {
+            entity::remove_component(player_id, components::inventory());
+        }
+    });
+}
+
+fn buy_item(shop: &Shop, map: &MapData, player_id: EntityId, item_id: u32) {
+    let item = match shop.item(item_id) {
+        Some(item) => item,
+        None => {
+            println!(
+                "{:?} tried to buy item {}, which is not in the shop",
+                player_id, item_id
+            );
+            return;
+        }
+    };
+    let (model, mut inventory) = match hero_and_inventory(player_id) {
+        Some(hero_and_inventory) => hero_and_inventory,
+        None => return,
+    };
+    if !is_near_own_base(map, shop, model) {
+        println!(
+            "{:?} tried to buy {} away from the shop",
+            player_id, item.name
+        );
+        return;
+    }
+    let gold = entity::get_component(player_id, components::gold()).unwrap_or(0);
+    if gold < item.cost {
+        println!("{:?} can't afford {}", player_id, item.name);
+        return;
+    }
+    let slot = match inventory.iter().position(|slot| *slot == EMPTY_SLOT) {
+        Some(slot) => slot,
+        None => {
+            println!("{:?} has no room for {}", player_id, item.name);
+            return;
+        }
+    };
+
+    inventory[slot] = item_id;
+    entity::set_component(player_id, components::inventory(), inventory);
+    entity::set_component(player_id, components::gold(), gold - item.cost);
+    apply_health_modifier(model, item.modifiers, 1);
+}
+
+fn sell_item(shop: &Shop, map: &MapData, player_id: EntityId, slot: u32) {
+    let (model, mut inventory) = match hero_and_inventory(player_id) {
+        Some(hero_and_inventory) => hero_and_inventory,
+        None => return,
+    };
+    let item = match inventory
+        .get(slot as usize)
+        .and_then(|item_id| shop.item(*item_id))
+    {
+        Some(item) => item,
+        None => {
+            println!("{:?} tried to sell empty slot {}", player_id, slot);
+            return;
+        }
+    };
+    if !is_near_own_base(map, shop, model) {
+        println!(
+            "{:?} tried to sell {} away from the shop",
+            player_id, item.name
+        );
+        return;
+    }
+
+    inventory[slot as usize] = EMPTY_SLOT;
+    entity::set_component(player_id, components::inventory(), inventory);
+    let gold = entity::get_component(player_id, components::gold()).unwrap_or(0);
+    entity::set_component(player_id, components::gold(), gold + shop.sell_price(item));
+    apply_health_modifier(model, item.modifiers, -1);
+}
+
+fn use_item(shop: &Shop, player_id: EntityId, slot: u32) {
+    let (model, mut inventory) = match hero_and_inventory(player_id) {
+        Some(hero_and_inventory) => hero_and_inventory,
+        None => return,
+    };
+    let item = match inventory
+        .get(slot as usize)
+        .and_then(|item_id| shop.item(*item_id))
+    {
+        Some(item) => item,
+        None => return,
+    };
+    let effect = match item.active {
+        Some(effect) => effect,
+        None => return,
+    };
+    if entity::has_component(model, components::is_dead()) {
+        return;
+    }
+
+    match effect {
+        ActiveEffect::Heal { amount } => {
+            let health = entity::get_component(model, components::health()).unwrap();
+            let max_health = entity::get_component(model, components::max_health()).unwrap();
+            entity::set_component(
+                model,
+                components::health(),
+                (health + amount).min(max_health),
+            );
+        }
+        ActiveEffect::Status(status) => {
+            messages::ApplyStatusEffect::new(
+                model,
+                status.kind as u32,
+                status.magnitude,
+                status.duration,
+                model,
+            )
+            .send_local_broadcast(false);
+        }
+    }
+
+    // items with an active effect are used up
+    inventory[slot as usize] = EMPTY_SLOT;
+    entity::set_component(player_id, components::inventory(), inventory);
+    apply_health_modifier(model, item.modifiers, -1);
+}
+
+fn hero_and_inventory(player_id: EntityId) -> Option<(EntityId, Vec<u32>)> {
+    let model = entity::get_component(player_id, components::hero_model())?;
+    let inventory = entity::get_component(player_id, components::inventory())?;
+    Some((model, inventory))
+}
+
+fn is_near_own_base(map: &MapData, shop: &Shop, model: EntityId) -> bool {
+    let team = entity::get_component(model, components::team()).unwrap();
+    let position = entity::get_component(model, translation()).unwrap().xy();
+    map.base_of_team(team).map_or(false, |base| {
+        base.position.distance(position) <= shop.shop_radius
+    })
+}
+
+// attack damage and speed are read from the inventory by the stats module, see `hero_bonus` there,
+// health is stored on the hero so it is changed here, `sign` being 1 when the item comes and -1 when it goes
+fn apply_health_modifier(model: EntityId, modifiers: StatModifiers, sign: i32) {
+    if modifiers.health == 0 {
+        return;
+    }
+    let change = modifiers.health * sign;
+    let max_health = entity::get_component(model, components::max_health()).unwrap();
+    let health = entity::get_component(model, components::health()).unwrap();
+    entity::set_component(model, components::max_health(), max_health + change);
+    // a dead hero gets its health back on respawn, and selling an item never kills its owner
+    if !entity::has_component(model, components::is_dead()) {
+        entity::set_component(model, components::health(), (health + change).max(1));
+    }
+}
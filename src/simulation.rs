@@ -0,0 +1,638 @@
+//! A match between the lanes without the engine: creep waves walking and fighting, towers and
+//! bases, played by fixed steps of [`TIME_STEP`].
+//!
+//! A [`Simulation`] keeps its own units, made from the same map and creep roster as the game,
+//! and decides what its creeps and towers do with the same [`behaviour`](crate::behaviour) and
+//! [`rules`](crate::rules) as the creeps and towers modules. There are no heroes, camps, items or status effects, and units have no
+//! colliders: steering alone keeps creeps apart and around towers, bases and obstacles.
+//! Nothing is random, so a simulation always plays out the same way, which is what the tests
+//! in `tests/simulation.rs` build on.
+//!
+//! ```
+//! use amoba::{creeps::CreepRoster, map_data::MapData, simulation::Simulation};
+//!
+//! let mut simulation = Simulation::new(&MapData::load(), &CreepRoster::load());
+//! simulation.run_for(60.);
+//! assert_eq!(simulation.wave_number(), 3);
+//! ```
+
+use glam::Vec2;
+
+use crate::{
+    behaviour::{self, CreepState, SeenUnit, Senses, Surroundings},
+    creeps::{CreepKind, CreepRoster, WaveTimer},
+    map_data::{FormationDefinition, MapData, BASE_RADIUS, TOWER_WIDTH},
+    movement::fly_towards,
+    rules::{self, TowerSlot},
+    stats::{Stat, StatBlock},
+    steering::Obstacle,
+};
+
+/// Seconds played by each [`Simulation::step`].
+pub const TIME_STEP: f32 = 1. / 30.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnitId(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitKind {
+    Creep(CreepKind),
+    /// `tier` 0 is the outermost tower of the lane.
+    Tower {
+        lane: u32,
+        tier: u32,
+    },
+    Base,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unit {
+    pub id: UnitId,
+    pub kind: UnitKind,
+    pub team: u32,
+    pub position: Vec2,
+    pub radius: f32,
+    pub health: i32,
+    pub stats: StatBlock,
+    /// Seconds left before it can attack again.
+    pub attack_cooldown: f32,
+    /// Units per second of what it shoots. `None` for units hitting in melee.
+    pub projectile_speed: Option<f32>,
+    pub bounty: u32,
+    /// Can't be hit, like a tower behind another tower of its lane.
+    pub invulnerable: bool,
+    /// Gets the bounty if the unit dies.
+    pub last_attacker: Option<UnitId>,
+    /// Index in the map of the lane a creep walks.
+    pub lane: Option<u32>,
+    /// `None` for towers and bases.
+    pub state: Option<CreepState<UnitId>>,
+    /// Where a creep left its lane to chase something.
+    pub leash_origin: Option<Vec2>,
+    /// What a tower keeps shooting while it is in range.
+    pub tower_target: Option<UnitId>,
+}
+
+/// How a match ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The team that destroyed the other's base.
+    Won(u32),
+    /// Both bases fell in the same step.
+    Draw,
+}
+
+/// A projectile homing on its target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Projectile {
+    pub owner: UnitId,
+    pub target: UnitId,
+    pub position: Vec2,
+    pub speed: f32,
+    pub damage: i32,
+}
+
+/// What happened during a simulation, with the time it happened at.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    WaveSent {
+        wave: u32,
+        time: f32,
+    },
+    Killed {
+        unit: UnitId,
+        kind: UnitKind,
+        team: u32,
+        killer: Option<UnitId>,
+        bounty: u32,
+        time: f32,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Lane {
+    team: u32,
+    path: Vec<Vec2>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    roster: CreepRoster,
+    ground_size: f32,
+    lanes: Vec<Lane>,
+    formation: FormationDefinition,
+    obstacles: Vec<Obstacle>,
+    /// (team, lane) of every lane that started with towers.
+    defended_lanes: Vec<(u32, u32)>,
+    steps: u64,
+    waves: WaveTimer,
+    /// Sorted by id, as ids only grow.
+    units: Vec<Unit>,
+    projectiles: Vec<Projectile>,
+    events: Vec<Event>,
+    next_id: u32,
+    outcome: Option<Outcome>,
+}
+
+// what a creep learns from the simulation rather than from the surroundings
+struct CreepSenses<'a> {
+    simulation: &'a Simulation,
+    surroundings: &'a Surroundings<UnitId>,
+    creep: &'a Unit,
+}
+
+impl Senses<UnitId> for CreepSenses<'_> {
+    // there are no heroes to go after or to defend
+    fn find_target(&self) -> Option<UnitId> {
+        let creep = self.creep;
+        let sight_range = creep.stats[Stat::SightRange];
+        self.surroundings
+            .closest_enemy(creep.team, creep.position, sight_range)
+    }
+
+    fn position_of(&self, unit: UnitId) -> Option<Vec2> {
+        self.simulation.unit(unit).map(|unit| unit.position)
+    }
+}
+
+impl Simulation {
+    /// A match at its start, with the towers and bases of `map` and no creeps yet.
+    pub fn new(map: &MapData, roster: &CreepRoster) -> Self {
+        let mut simulation = Self {
+            roster: roster.clone(),
+            ground_size: map.ground_size,
+            lanes: map
+                .lanes
+                .iter()
+                .map(|lane| Lane {
+                    team: map.lane_team(lane),
+                    path: map.lane_path(lane),
+                })
+                .collect(),
+            formation: map.formation.clone(),
+            obstacles: map
+                .obstacles
+                .iter()
+                .map(|obstacle| Obstacle::Box {
+                    center: obstacle.position,
+                    size: obstacle.size,
+                })
+                .collect(),
+            defended_lanes: Vec::new(),
+            steps: 0,
+            waves: WaveTimer::default(),
+            units: Vec::new(),
+            projectiles: Vec::new(),
+            events: Vec::new(),
+            next_id: 0,
+            outcome: None,
+        };
+
+        for base in &map.bases {
+            simulation.add_unit(
+                UnitKind::Base,
+                base.team,
+                base.position,
+                BASE_RADIUS,
+                base.health,
+                StatBlock::ZERO,
+                None,
+                0,
+            );
+        }
+        for (lane_index, lane) in map.lanes.iter().enumerate() {
+            let team = map.lane_team(lane);
+            for (tier, tower) in map.towers_of_lane(lane).enumerate() {
+                let kind = UnitKind::Tower {
+                    lane: lane_index as u32,
+                    tier: tier as u32,
+                };
+                simulation.add_unit(
+                    kind,
+                    team,
+                    tower.position,
+                    TOWER_WIDTH / 2.,
+                    map.tower.health,
+                    map.tower.base_stats(),
+                    Some(map.tower.projectile_speed),
+                    map.tower.bounty,
+                );
+                if tier == 0 {
+                    simulation.defended_lanes.push((team, lane_index as u32));
+                }
+            }
+        }
+        simulation
+    }
+
+    /// Plays [`TIME_STEP`] seconds, unless the match is over.
+    pub fn step(&mut self) {
+        if self.outcome.is_some() {
+            return;
+        }
+        self.steps += 1;
+
+        if let Some(wave) = self.waves.tick(TIME_STEP, &self.roster.waves) {
+            self.send_wave(wave);
+        }
+        self.protect_towers_and_bases();
+
+        let surroundings = self.surroundings();
+        self.creeps_act(&surroundings);
+        self.towers_act(&surroundings);
+        self.fly_projectiles();
+        self.remove_dead();
+    }
+
+    /// Plays `seconds` seconds, or until the match is over.
+    pub fn run_for(&mut self, seconds: f32) {
+        let end = self.steps + (seconds / TIME_STEP).round() as u64;
+        while self.steps < end && self.outcome.is_none() {
+            self.step();
+        }
+    }
+
+    /// Seconds played.
+    pub fn time(&self) -> f32 {
+        self.steps as f32 * TIME_STEP
+    }
+
+    /// Waves sent so far.
+    pub fn wave_number(&self) -> u32 {
+        self.waves.wave_number
+    }
+
+    /// Every unit still standing, by id.
+    pub fn units(&self) -> &[Unit] {
+        &self.units
+    }
+
+    pub fn unit(&self, id: UnitId) -> Option<&Unit> {
+        self.units
+            .binary_search_by_key(&id, |unit| unit.id)
+            .ok()
+            .map(|index| &self.units[index])
+    }
+
+    pub fn projectiles(&self) -> &[Projectile] {
+        &self.projectiles
+    }
+
+    /// Everything that happened so far, in order.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// How the match ended, once a base fell.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    /// Team that destroyed the other's base, once one did. `None` after a draw too.
+    pub fn winner(&self) -> Option<u32> {
+        match self.outcome {
+            Some(Outcome::Won(team)) => Some(team),
+            Some(Outcome::Draw) | None => None,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_unit(
+        &mut self,
+        kind: UnitKind,
+        team: u32,
+        position: Vec2,
+        radius: f32,
+        health: i32,
+        stats: StatBlock,
+        projectile_speed: Option<f32>,
+        bounty: u32,
+    ) -> &mut Unit {
+        let id = UnitId(self.next_id);
+        self.next_id += 1;
+        self.units.push(Unit {
+            id,
+            kind,
+            team,
+            position,
+            radius,
+            health,
+            stats,
+            attack_cooldown: 0.,
+            projectile_speed,
+            bounty,
+            invulnerable: false,
+            last_attacker: None,
+            lane: None,
+            state: None,
+            leash_origin: None,
+            tower_target: None,
+        });
+        self.units.last_mut().unwrap()
+    }
+
+    // every lane sends the same wave, lined up behind its spawn point like in the creeps module
+    fn send_wave(&mut self, wave: u32) {
+        let kinds = self.roster.waves.composition(wave);
+        for lane_index in 0..self.lanes.len() {
+            let lane = &self.lanes[lane_index];
+            let (team, spawn) = (lane.team, lane.path[0]);
+            let forward = lane.path.get(1).map_or(Vec2::ZERO, |next| *next - spawn);
+            let next_point = 1.min(lane.path.len() - 1);
+
+            for (slot, kind) in kinds.iter().enumerate() {
+                let definition = self.roster.kind(*kind);
+                let position = spawn + self.formation.slot_offset(slot, forward);
+                let creep = self.add_unit(
+                    UnitKind::Creep(*kind),
+                    team,
+                    position,
+                    definition.radius,
+                    definition.health,
+                    definition.base_stats(),
+                    definition.projectile_speed,
+                    definition.bounty,
+                );
+                creep.lane = Some(lane_index as u32);
+                creep.state = Some(CreepState::Move { next_point });
+            }
+        }
+        self.events.push(Event::WaveSent {
+            wave,
+            time: self.time(),
+        });
+    }
+
+    fn protect_towers_and_bases(&mut self) {
+        let standing: Vec<TowerSlot> = self
+            .units
+            .iter()
+            .filter_map(|unit| match unit.kind {
+                UnitKind::Tower { lane, tier } => Some(TowerSlot {
+                    team: unit.team,
+                    lane,
+                    tier,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        for unit in &mut self.units {
+            unit.invulnerable = match unit.kind {
+                UnitKind::Tower { lane, tier } => rules::is_tower_protected(
+                    TowerSlot {
+                        team: unit.team,
+                        lane,
+                        tier,
+                    },
+                    &standing,
+                ),
+                UnitKind::Base => {
+                    rules::is_base_protected(unit.team, &self.defended_lanes, &standing)
+                }
+                UnitKind::Creep(_) => false,
+            };
+        }
+    }
+
+    fn surroundings(&self) -> Surroundings<UnitId> {
+        let mut surroundings = Surroundings::new(
+            self.ground_size,
+            self.roster.steering,
+            self.roster.targeting.clone(),
+            &self.obstacles,
+        );
+        for unit in &self.units {
+            let seen = SeenUnit {
+                id: unit.id,
+                team: unit.team,
+                position: unit.position,
+                radius: unit.radius,
+                targetable: !unit.invulnerable,
+            };
+            match unit.kind {
+                UnitKind::Creep(_) => surroundings.add_creep(seen),
+                UnitKind::Tower { .. } => surroundings.add_tower(seen),
+                UnitKind::Base => surroundings.add_base(seen),
+            }
+        }
+        surroundings
+    }
+
+    fn creeps_act(&mut self, surroundings: &Surroundings<UnitId>) {
+        let actions: Vec<(usize, behaviour::CreepAction<UnitId>)> = self
+            .units
+            .iter()
+            .enumerate()
+            .filter_map(|(index, unit)| {
+                let path = &self.lanes[unit.lane? as usize].path;
+                let creep = behaviour::Creep {
+                    id: unit.id,
+                    team: unit.team,
+                    position: unit.position,
+                    radius: unit.radius,
+                    stats: unit.stats,
+                    attack_cooldown: unit.attack_cooldown,
+                    leash_origin: unit.leash_origin,
+                    lane: Some(path),
+                };
+                let senses = CreepSenses {
+                    simulation: self,
+                    surroundings,
+                    creep: unit,
+                };
+                let action =
+                    behaviour::creep_action(&creep, unit.state?, surroundings, &senses, TIME_STEP);
+                Some((index, action))
+            })
+            .collect();
+
+        let mut hits = Vec::new();
+        for (index, action) in actions {
+            let creep = &mut self.units[index];
+            creep.state = Some(action.state);
+            creep.leash_origin = action.leash_origin;
+            creep.attack_cooldown = action.attack_cooldown;
+            creep.position += action.velocity * TIME_STEP;
+
+            let Some(target) = action.attack else {
+                continue;
+            };
+            let damage = creep.stats[Stat::AttackDamage].round() as i32;
+            // ranged and siege creeps shoot, the projectile deals the damage when it arrives
+            match creep.projectile_speed {
+                Some(speed) => self.projectiles.push(Projectile {
+                    owner: creep.id,
+                    target,
+                    position: creep.position,
+                    speed,
+                    damage,
+                }),
+                None => hits.push((target, damage, creep.id)),
+            }
+        }
+
+        for (target, damage, attacker) in hits {
+            self.deal_damage(target, damage, attacker);
+        }
+    }
+
+    // towers keep shooting their target while it is in range, or else the closest enemy creep
+    fn towers_act(&mut self, surroundings: &Surroundings<UnitId>) {
+        for index in 0..self.units.len() {
+            let tower = &self.units[index];
+            if !matches!(tower.kind, UnitKind::Tower { .. }) {
+                continue;
+            }
+            let range = tower.stats[Stat::AttackRange];
+            let in_range = |target: UnitId| {
+                self.unit(target)
+                    .is_some_and(|target| target.position.distance(tower.position) <= range)
+            };
+            let action = behaviour::tower_action(
+                tower.tower_target,
+                in_range,
+                || surroundings.closest_enemy_creep(tower.team, tower.position, range),
+                tower.attack_cooldown,
+                tower.stats[Stat::AttackInterval],
+                TIME_STEP,
+            );
+
+            let tower = &mut self.units[index];
+            tower.tower_target = action.target;
+            tower.attack_cooldown = action.attack_cooldown;
+            if let (true, Some(target)) = (action.attacks, action.target) {
+                self.projectiles.push(Projectile {
+                    owner: tower.id,
+                    target,
+                    position: tower.position,
+                    speed: tower.projectile_speed.unwrap(),
+                    damage: tower.stats[Stat::AttackDamage].round() as i32,
+                });
+            }
+        }
+    }
+
+    fn fly_projectiles(&mut self) {
+        let mut hits = Vec::new();
+        let units = &self.units;
+        let find = |id: UnitId| {
+            units
+                .binary_search_by_key(&id, |unit| unit.id)
+                .ok()
+                .map(|index| &units[index])
+        };
+
+        // a projectile whose target died on the way fizzles out
+        self.projectiles.retain_mut(|projectile| {
+            let Some(target) = find(projectile.target) else {
+                return false;
+            };
+            let flight = fly_towards(
+                projectile.position.extend(0.),
+                target.position.extend(0.),
+                projectile.speed,
+                TIME_STEP,
+            );
+            if let Some(position) = flight {
+                projectile.position = position.truncate();
+                return true;
+            }
+            hits.push((projectile.target, projectile.damage, projectile.owner));
+            false
+        });
+
+        for (target, damage, owner) in hits {
+            self.deal_damage(target, damage, owner);
+        }
+    }
+
+    fn deal_damage(&mut self, target: UnitId, damage: i32, attacker: UnitId) {
+        let Ok(index) = self.units.binary_search_by_key(&target, |unit| unit.id) else {
+            return;
+        };
+        let unit = &mut self.units[index];
+        if unit.invulnerable {
+            return;
+        }
+        unit.health -= damage;
+        unit.last_attacker = Some(attacker);
+    }
+
+    // the dead are gone at the end of the step, and the match is over once a base fell. If the
+    // last bases fall together, nobody won
+    fn remove_dead(&mut self) {
+        let time = self.time();
+        for unit in self.units.iter().filter(|unit| unit.health <= 0) {
+            self.events.push(Event::Killed {
+                unit: unit.id,
+                kind: unit.kind,
+                team: unit.team,
+                killer: unit.last_attacker,
+                bounty: unit.bounty,
+                time,
+            });
+        }
+        let base_fell = self
+            .units
+            .iter()
+            .any(|unit| unit.kind == UnitKind::Base && unit.health <= 0);
+        self.units.retain(|unit| unit.health > 0);
+
+        if base_fell {
+            let standing = self.units.iter().find(|unit| unit.kind == UnitKind::Base);
+            self.outcome = Some(match standing {
+                Some(base) => Outcome::Won(base.team),
+                None => Outcome::Draw,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulation() -> Simulation {
+        Simulation::new(&MapData::load(), &CreepRoster::load())
+    }
+
+    fn destroy_bases(simulation: &mut Simulation, teams: &[u32]) {
+        for unit in &mut simulation.units {
+            if unit.kind == UnitKind::Base && teams.contains(&unit.team) {
+                unit.invulnerable = false;
+                unit.health = 0;
+            }
+        }
+    }
+
+    #[test]
+    fn match_goes_on_while_both_bases_stand() {
+        let mut simulation = simulation();
+        simulation.run_for(1.);
+        assert_eq!(simulation.outcome(), None);
+    }
+
+    #[test]
+    fn team_with_the_last_base_wins() {
+        let mut simulation = simulation();
+        destroy_bases(&mut simulation, &[1]);
+        simulation.remove_dead();
+        assert_eq!(simulation.outcome(), Some(Outcome::Won(0)));
+        assert_eq!(simulation.winner(), Some(0));
+    }
+
+    #[test]
+    fn bases_falling_together_end_in_a_draw() {
+        let mut simulation = simulation();
+        destroy_bases(&mut simulation, &[0, 1]);
+        simulation.remove_dead();
+        assert_eq!(simulation.outcome(), Some(Outcome::Draw));
+        assert_eq!(simulation.winner(), None);
+
+        // and nothing plays after it
+        let time = simulation.time();
+        simulation.run_for(10.);
+        assert_eq!(simulation.time(), time);
+    }
+}
@@ -0,0 +1,275 @@
+//! Where units are, for the "who is around here" questions of targeting and area damage.
+//!
+//! Going through every unit to find the closest enemy costs as much as there are units, for
+//! every unit that looks, so a few hundred creeps make tens of thousands of checks a frame.
+//! A [`SpatialGrid`] sorts units into square cells once per frame, and a question only looks
+//! at the cells it overlaps. The closest unit is looked for ring of cells by ring of cells
+//! around the question, stopping as soon as no further cell can hold anything closer.
+//!
+//! `cargo bench --bench spatial` compares both with a few hundred units.
+
+use glam::Vec2;
+
+#[derive(Debug, Clone)]
+pub struct SpatialGrid<T> {
+    /// Position of the corner of the first cell.
+    origin: Vec2,
+    cell_size: f32,
+    width: usize,
+    cells: Vec<Vec<(Vec2, T)>>,
+    len: usize,
+}
+
+impl<T> SpatialGrid<T> {
+    /// A grid covering a square of `size`, centered on the origin. Units off it are kept in
+    /// the cells of its border, so they are still found, only more slowly.
+    pub fn new(size: f32, cell_size: f32) -> Self {
+        if cell_size <= 0. {
+            panic!("spatial grid cells must be bigger than 0, not {cell_size}");
+        }
+        let width = (size / cell_size).ceil().max(1.) as usize;
+        Self {
+            origin: Vec2::splat(-size / 2.),
+            cell_size,
+            width,
+            cells: (0..width * width).map(|_| Vec::new()).collect(),
+            len: 0,
+        }
+    }
+
+    pub fn insert(&mut self, position: Vec2, item: T) {
+        let (x, y) = self.cell(position);
+        self.cells[y * self.width + x].push((position, item));
+        self.len += 1;
+    }
+
+    /// Empties the grid but keeps its memory, for the next frame.
+    pub fn clear(&mut self) {
+        for cell in &mut self.cells {
+            cell.clear();
+        }
+        self.len = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Everything at most `radius` away from `center`, in no particular order.
+    pub fn within(&self, center: Vec2, radius: f32) -> impl Iterator<Item = (Vec2, &T)> + '_ {
+        let min = self.cell(center - Vec2::splat(radius));
+        let max = self.cell(center + Vec2::splat(radius));
+        (min.1..=max.1)
+            .flat_map(move |y| (min.0..=max.0).map(move |x| (x, y)))
+            .flat_map(|(x, y)| &self.cells[y * self.width + x])
+            .filter(move |(position, _)| position.distance_squared(center) <= radius * radius)
+            .map(|(position, item)| (*position, item))
+    }
+
+    /// The closest item at most `radius` away from `center` that `filter` accepts.
+    pub fn nearest(
+        &self,
+        center: Vec2,
+        radius: f32,
+        filter: impl Fn(&T) -> bool,
+    ) -> Option<(Vec2, &T)> {
+        let (cx, cy) = self.cell(center);
+        let (cx, cy) = (cx as i64, cy as i64);
+        let rings = (radius / self.cell_size).ceil() as i64 + 1;
+
+        let mut best: Option<(f32, Vec2, &T)> = None;
+        for ring in 0..=rings.min(self.width as i64) {
+            for (x, y) in ring_cells(cx, cy, ring) {
+                if x < 0 || y < 0 || x >= self.width as i64 || y >= self.width as i64 {
+                    continue;
+                }
+                for (position, item) in &self.cells[y as usize * self.width + x as usize] {
+                    let distance = position.distance_squared(center);
+                    let closer = match best {
+                        Some((best_distance, _, _)) => distance < best_distance,
+                        None => true,
+                    };
+                    if closer && distance <= radius * radius && filter(item) {
+                        best = Some((distance, *position, item));
+                    }
+                }
+            }
+            // anything in the next ring is at least `ring` cells away
+            let next_ring = ring as f32 * self.cell_size;
+            if best.is_some_and(|(distance, _, _)| distance <= next_ring * next_ring) {
+                break;
+            }
+        }
+        best.map(|(_, position, item)| (position, item))
+    }
+
+    fn cell(&self, position: Vec2) -> (usize, usize) {
+        let cell = ((position - self.origin) / self.cell_size).max(Vec2::ZERO);
+        (
+            (cell.x as usize).min(self.width - 1),
+            (cell.y as usize).min(self.width - 1),
+        )
+    }
+}
+
+impl<T> Extend<(Vec2, T)> for SpatialGrid<T> {
+    fn extend<I: IntoIterator<Item = (Vec2, T)>>(&mut self, items: I) {
+        for (position, item) in items {
+            self.insert(position, item);
+        }
+    }
+}
+
+// the cells exactly `ring` cells away from (x, y): the rows above and below, then the columns on
+// each side without their corners
+fn ring_cells(x: i64, y: i64, ring: i64) -> impl Iterator<Item = (i64, i64)> {
+    let rows = (-ring..=ring).flat_map(move |offset| {
+        let below = (x + offset, y - ring);
+        let above = (x + offset, y + ring);
+        [Some(below), (ring > 0).then_some(above)]
+            .into_iter()
+            .flatten()
+    });
+    let columns =
+        (1 - ring..ring).flat_map(move |offset| [(x - ring, y + offset), (x + ring, y + offset)]);
+    rows.chain(columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: f32 = 40.;
+    const CELL_SIZE: f32 = 2.5;
+
+    // the same points on every run, without pulling in a random number crate
+    fn points(count: usize) -> Vec<Vec2> {
+        let mut state: u32 = 12345;
+        let mut next = move || {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (state >> 8) as f32 / (1 << 24) as f32
+        };
+        (0..count)
+            .map(|_| Vec2::new(next(), next()) * (SIZE + 10.) - (SIZE + 10.) / 2.)
+            .collect()
+    }
+
+    // points right on the borders of cells and a hair off them, on both sides
+    fn border_points() -> Vec<Vec2> {
+        let mut points = Vec::new();
+        for line in [-5., 0., 2.5, 7.5] {
+            for offset in [-1e-3, 0., 1e-3] {
+                points.push(Vec2::new(line + offset, 1.25));
+                points.push(Vec2::new(1.25, line + offset));
+                points.push(Vec2::splat(line + offset));
+            }
+        }
+        points
+    }
+
+    fn grid(points: &[Vec2]) -> SpatialGrid<usize> {
+        let mut grid = SpatialGrid::new(SIZE, CELL_SIZE);
+        grid.extend(points.iter().copied().zip(0..));
+        grid
+    }
+
+    fn brute_within(points: &[Vec2], center: Vec2, radius: f32) -> Vec<usize> {
+        (0..points.len())
+            .filter(|index| points[*index].distance_squared(center) <= radius * radius)
+            .collect()
+    }
+
+    fn brute_nearest(
+        points: &[Vec2],
+        center: Vec2,
+        radius: f32,
+        filter: impl Fn(&usize) -> bool,
+    ) -> Option<f32> {
+        (0..points.len())
+            .filter(|index| filter(index))
+            .map(|index| points[index].distance_squared(center))
+            .filter(|distance| *distance <= radius * radius)
+            .min_by(f32::total_cmp)
+    }
+
+    fn centers() -> Vec<Vec2> {
+        let mut centers = points(50);
+        centers.extend(border_points());
+        centers
+    }
+
+    #[test]
+    fn within_finds_the_same_as_brute_force() {
+        let mut all = points(300);
+        all.extend(border_points());
+        let grid = grid(&all);
+        assert_eq!(grid.len(), all.len());
+
+        for center in centers() {
+            for radius in [0., 1., CELL_SIZE, 4., 12.5] {
+                let mut found: Vec<usize> = grid.within(center, radius).map(|(_, i)| *i).collect();
+                found.sort();
+                assert_eq!(
+                    found,
+                    brute_within(&all, center, radius),
+                    "around {center} within {radius}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_finds_the_same_as_brute_force() {
+        let mut all = points(300);
+        all.extend(border_points());
+        let grid = grid(&all);
+
+        for center in centers() {
+            for radius in [1., CELL_SIZE, 4., 12.5, 100.] {
+                let even = |index: &usize| index.is_multiple_of(2);
+                let found = grid
+                    .nearest(center, radius, even)
+                    .map(|(position, _)| position.distance_squared(center));
+                assert_eq!(
+                    found,
+                    brute_nearest(&all, center, radius, even),
+                    "around {center} within {radius}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn points_exactly_at_the_radius_count() {
+        let center = Vec2::new(1., 1.);
+        let on_edge = [Vec2::new(3.5, 1.), Vec2::new(1., -1.5), Vec2::new(-1., 3.)];
+        let grid = grid(&on_edge);
+
+        assert_eq!(grid.within(center, 2.5).count(), 2);
+        assert_eq!(grid.within(center, 2.5 - 1e-3).count(), 0);
+        assert_eq!(
+            grid.nearest(center, 2.5, |_| true)
+                .unwrap()
+                .0
+                .distance(center),
+            2.5
+        );
+        assert!(grid.nearest(center, 2.5 - 1e-3, |_| true).is_none());
+        // the corner of the square around the circle is further than the radius
+        assert!(grid
+            .within(center, 2.5)
+            .all(|(position, _)| position != on_edge[2]));
+    }
+
+    #[test]
+    fn clear_keeps_nothing() {
+        let mut grid = grid(&points(20));
+        grid.clear();
+        assert!(grid.is_empty());
+        assert!(grid.nearest(Vec2::ZERO, 100., |_| true).is_none());
+    }
+}
@@ -0,0 +1,208 @@
+//! States for units that change what they do with what happens around them, like creeps
+//! walking their lane, chasing an enemy and hitting it.
+//!
+//! A state is registered under a [`StateId`], the number kept in the unit's state component.
+//! Every frame, [`StateMachine::step`] first follows the first automatic transition of the
+//! current state whose guard passes, e.g. to a stunned state, then lets the state update. The
+//! update may ask for another state, which happens if that transition was allowed and its
+//! guard passes. States and transitions can be added to a machine without touching the ones
+//! already there.
+
+use std::collections::BTreeMap;
+
+/// Kept in the unit's state component as its `u16` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StateId(pub u16);
+
+/// What a unit does while in a state. `C` is everything the state needs to know about the unit.
+pub trait State<C> {
+    fn name(&self) -> &'static str;
+
+    /// Called once when the unit comes into the state.
+    fn enter(&self, _context: &mut C) {}
+
+    /// Called every frame while the unit is in the state. Returns the state it wants to go to.
+    fn update(&self, _context: &mut C) -> Option<StateId> {
+        None
+    }
+
+    /// Called once when the unit leaves the state.
+    fn exit(&self, _context: &mut C) {}
+}
+
+type Guard<C> = Box<dyn Fn(&C) -> bool>;
+
+struct Transition<C> {
+    /// `None` for every state but `to`.
+    from: Option<StateId>,
+    to: StateId,
+    automatic: bool,
+    guard: Guard<C>,
+}
+
+impl<C> Transition<C> {
+    fn leaves(&self, state: StateId) -> bool {
+        let from_state = match self.from {
+            Some(from) => from == state,
+            None => true,
+        };
+        self.to != state && from_state
+    }
+}
+
+/// ```
+/// use amoba::state_machine::{State, StateId, StateMachine};
+///
+/// const WALK: StateId = StateId(0);
+/// const REST: StateId = StateId(1);
+///
+/// struct Walker {
+///     stamina: u32,
+/// }
+///
+/// struct Walk;
+/// impl State<Walker> for Walk {
+///     fn name(&self) -> &'static str {
+///         "walk"
+///     }
+///     fn update(&self, walker: &mut Walker) -> Option<StateId> {
+///         walker.stamina -= 1;
+///         Some(REST)
+///     }
+/// }
+///
+/// struct Rest;
+/// impl State<Walker> for Rest {
+///     fn name(&self) -> &'static str {
+///         "rest"
+///     }
+///     fn update(&self, walker: &mut Walker) -> Option<StateId> {
+///         walker.stamina += 1;
+///         None
+///     }
+/// }
+///
+/// let mut machine = StateMachine::new();
+/// machine.add_state(WALK, Walk);
+/// machine.add_state(REST, Rest);
+/// // walking asks to rest every frame, but only gets to when tired
+/// machine.allow(WALK, REST, |walker| walker.stamina < 2);
+/// // rested walkers go back on their own
+/// machine.when(REST, WALK, |walker| walker.stamina >= 3);
+///
+/// let mut walker = Walker { stamina: 3 };
+/// let mut state = WALK;
+/// let mut states = Vec::new();
+/// for _ in 0..5 {
+///     state = machine.step(state, &mut walker);
+///     states.push(machine.name(state).unwrap());
+/// }
+/// assert_eq!(states, ["walk", "rest", "rest", "rest", "walk"]);
+/// ```
+pub struct StateMachine<C> {
+    states: BTreeMap<StateId, Box<dyn State<C>>>,
+    transitions: Vec<Transition<C>>,
+}
+
+impl<C> Default for StateMachine<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> StateMachine<C> {
+    pub fn new() -> Self {
+        Self {
+            states: BTreeMap::new(),
+            transitions: Vec::new(),
+        }
+    }
+
+    /// Panics if `id` is already taken, two states can't share it.
+    pub fn add_state(&mut self, id: StateId, state: impl State<C> + 'static) {
+        if let Some(taken) = self.states.get(&id) {
+            panic!("state {} is already used by {}", id.0, taken.name());
+        }
+        self.states.insert(id, Box::new(state));
+    }
+
+    /// Lets a unit in `from` go to `to` when its update asks for it and `guard` passes.
+    pub fn allow(&mut self, from: StateId, to: StateId, guard: impl Fn(&C) -> bool + 'static) {
+        self.add_transition(Some(from), to, false, guard);
+    }
+
+    /// Sends a unit in `from` to `to` as soon as `guard` passes.
+    pub fn when(&mut self, from: StateId, to: StateId, guard: impl Fn(&C) -> bool + 'static) {
+        self.add_transition(Some(from), to, true, guard);
+    }
+
+    /// Sends a unit in any other state to `to` as soon as `guard` passes.
+    pub fn from_any_when(&mut self, to: StateId, guard: impl Fn(&C) -> bool + 'static) {
+        self.add_transition(None, to, true, guard);
+    }
+
+    pub fn name(&self, id: StateId) -> Option<&'static str> {
+        self.states.get(&id).map(|state| state.name())
+    }
+
+    /// Runs a frame of a unit in `current`, and returns the state it is in afterwards.
+    /// Panics if `current` was never added.
+    pub fn step(&self, current: StateId, context: &mut C) -> StateId {
+        let mut current = current;
+
+        let automatic = self
+            .transitions
+            .iter()
+            .find(|transition| {
+                transition.automatic && transition.leaves(current) && (transition.guard)(context)
+            })
+            .map(|transition| transition.to);
+        if let Some(next) = automatic {
+            self.change(current, next, context);
+            current = next;
+        }
+
+        if let Some(asked) = self.state(current).update(context) {
+            let allowed = self.transitions.iter().any(|transition| {
+                !transition.automatic
+                    && transition.to == asked
+                    && transition.leaves(current)
+                    && (transition.guard)(context)
+            });
+            if allowed {
+                self.change(current, asked, context);
+                current = asked;
+            }
+        }
+
+        current
+    }
+
+    /// Leaves `from` and enters `to`, whatever the transitions say.
+    pub fn change(&self, from: StateId, to: StateId, context: &mut C) {
+        self.state(from).exit(context);
+        self.state(to).enter(context);
+    }
+
+    fn add_transition(
+        &mut self,
+        from: Option<StateId>,
+        to: StateId,
+        automatic: bool,
+        guard: impl Fn(&C) -> bool + 'static,
+    ) {
+        self.transitions.push(Transition {
+            from,
+            to,
+            automatic,
+            guard: Box::new(guard),
+        });
+    }
+
+    fn state(&self, id: StateId) -> &dyn State<C> {
+        match self.states.get(&id) {
+            Some(state) => state.as_ref(),
+            None => panic!("state {} does not exist", id.0),
+        }
+    }
+}
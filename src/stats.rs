@@ -0,0 +1,108 @@
+//! The stats every unit fights and moves with.
+//!
+//! A unit spawns with its `base_stats`, taken from its definition in the data files. The
+//! stats module then adds what its items, buffs and level give it, applies the multipliers
+//! of its slows, and writes the result in the `stats` component, which is what movement,
+//! targeting and combat read. Both components hold one value per [`Stat`], in its order.
+//!
+//! Health is a pool rather than a stat: items and levels change `max_health` directly.
+
+use std::ops::{Index, IndexMut};
+
+/// Index of a stat in the `base_stats` and `stats` components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stat {
+    /// Units per second the unit walks.
+    MoveSpeed = 0,
+    /// Health removed by each attack. Stored as a float, rounded when dealt.
+    AttackDamage = 1,
+    /// How close the target must be to be attacked.
+    AttackRange = 2,
+    /// Seconds between two attacks.
+    AttackInterval = 3,
+    /// How far a creep looks for something to chase, and how far it follows it.
+    SightRange = 4,
+}
+
+impl Stat {
+    pub const COUNT: usize = 5;
+
+    pub const ALL: [Stat; Stat::COUNT] = [
+        Stat::MoveSpeed,
+        Stat::AttackDamage,
+        Stat::AttackRange,
+        Stat::AttackInterval,
+        Stat::SightRange,
+    ];
+}
+
+/// One value per stat.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatBlock([f32; Stat::COUNT]);
+
+impl StatBlock {
+    pub const ZERO: StatBlock = StatBlock([0.0; Stat::COUNT]);
+    pub const ONE: StatBlock = StatBlock([1.0; Stat::COUNT]);
+
+    /// Reads a `base_stats` or `stats` component. Missing stats are 0.
+    pub fn from_component(values: &[f32]) -> Self {
+        let mut block = Self::ZERO;
+        for (stat, value) in block.0.iter_mut().zip(values) {
+            *stat = *value;
+        }
+        block
+    }
+
+    pub fn to_component(self) -> Vec<f32> {
+        self.0.to_vec()
+    }
+}
+
+impl Default for StatBlock {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl Index<Stat> for StatBlock {
+    type Output = f32;
+
+    fn index(&self, stat: Stat) -> &f32 {
+        &self.0[stat as usize]
+    }
+}
+
+impl IndexMut<Stat> for StatBlock {
+    fn index_mut(&mut self, stat: Stat) -> &mut f32 {
+        &mut self.0[stat as usize]
+    }
+}
+
+/// The stats of a unit before they are put together.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnitStats {
+    pub base: StatBlock,
+    /// Added to the base: items, buffs, levels.
+    pub bonus: StatBlock,
+    /// What base and bonus are multiplied by: slows.
+    pub multiplier: StatBlock,
+}
+
+impl UnitStats {
+    pub fn new(base: StatBlock) -> Self {
+        Self {
+            base,
+            bonus: StatBlock::ZERO,
+            multiplier: StatBlock::ONE,
+        }
+    }
+
+    /// The final stats, never below 0.
+    pub fn total(&self) -> StatBlock {
+        let mut total = StatBlock::ZERO;
+        for stat in Stat::ALL {
+            total[stat] = ((self.base[stat] + self.bonus[stat]) * self.multiplier[stat]).max(0.0);
+        }
+        total
+    }
+}
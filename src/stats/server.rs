@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use ambient_api::{components::core::player::player, prelude::*};
+use amoba::{
+    roster::{HeroDefinition, HeroRoster},
+    shop::Shop,
+    stats::{Stat, StatBlock, UnitStats},
+};
+
+#[main]
+pub fn main() {
+    let roster = HeroRoster::load();
+    let shop = Shop::load();
+
+    stats_system(roster, shop);
+}
+
+fn stats_system(roster: HeroRoster, shop: Shop) {
+    let heroes_query = query((player(), components::hero_model(), components::role())).build();
+
+    query(components::base_stats()).each_frame(move |units| {
+        // levels and items are kept on the player, not on its hero
+        let hero_bonuses: HashMap<EntityId, StatBlock> = heroes_query
+            .evaluate()
+            .into_iter()
+            .filter_map(|(player_id, (_, model, role))| {
+                let hero = roster.hero(role)?;
+                Some((model, hero_bonus(&shop, player_id, hero)))
+            })
+            .collect();
+
+        for (unit, base) in units {
+            let mut stats = UnitStats::new(StatBlock::from_component(&base));
+            if let Some(bonus) = hero_bonuses.get(&unit) {
+                stats.bonus = *bonus;
+            }
+            if let Some(speed_bonus) = entity::get_component(unit, components::speed_bonus()) {
+                stats.bonus[Stat::MoveSpeed] += speed_bonus;
+            }
+            if let Some(speed_multiplier) =
+                entity::get_component(unit, components::speed_multiplier())
+            {
+                stats.multiplier[Stat::MoveSpeed] = speed_multiplier;
+            }
+
+            let total = stats.total().to_component();
+            if entity::get_component(unit, components::stats()).as_ref() != Some(&total) {
+                entity::add_component(unit, components::stats(), total);
+            }
+        }
+    });
+}
+
+// health is not in there: items and levels change max_health when they come
+fn hero_bonus(shop: &Shop, player_id: EntityId, hero: &HeroDefinition) -> StatBlock {
+    let level = entity::get_component(player_id, components::level()).unwrap_or(1);
+    let items = entity::get_component(player_id, components::inventory())
+        .map(|inventory| shop.modifiers(&inventory))
+        .unwrap_or_default();
+
+    let mut bonus = StatBlock::ZERO;
+    bonus[Stat::MoveSpeed] = hero.speed_at(level) - hero.speed + items.speed;
+    bonus[Stat::AttackDamage] =
+        (hero.attack_damage_at(level) - hero.attack_damage + items.attack_damage) as f32;
+    bonus
+}
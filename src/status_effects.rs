@@ -0,0 +1,380 @@
+//! Buffs and debuffs shared by abilities and items: slows, speed boosts, stuns, damage and
+//! healing over time, and shields.
+//!
+//! A unit keeps its effects in five parallel components, `status_kinds`,
+//! `status_magnitudes`, `status_durations`, `status_next_ticks` and `status_sources`, one
+//! entry per effect.
+//! [`StatusEffects`] reads them back into a list, applies the stacking rules and is turned
+//! back into components once changed.
+
+use serde::Deserialize;
+
+/// Seconds between two ticks of the damage and healing over time effects.
+pub const TICK_INTERVAL: f32 = 1.0;
+
+/// Stored in the `status_kinds` component as its `u32` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusKind {
+    /// Removes `magnitude` (between 0 and 1) of the unit's movement speed.
+    Slow = 0,
+    /// The unit can't move, attack or cast. `magnitude` is unused.
+    Stun = 1,
+    /// Deals `magnitude` damage every [`TICK_INTERVAL`].
+    DamageOverTime = 2,
+    /// Gives back `magnitude` health every [`TICK_INTERVAL`].
+    HealOverTime = 3,
+    /// Absorbs up to `magnitude` damage before the unit's health is touched.
+    Shield = 4,
+    /// Adds `magnitude` to the unit's movement speed.
+    SpeedBoost = 5,
+}
+
+/// What happens when an effect is applied to a unit that already has one of the same kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stacking {
+    /// Only one effect of the kind is kept: the strongest, then the longest.
+    Strongest,
+    /// One effect per source: the same source refreshes its own, different sources add up.
+    PerSource,
+}
+
+impl StatusKind {
+    pub const ALL: [StatusKind; 6] = [
+        StatusKind::Slow,
+        StatusKind::Stun,
+        StatusKind::DamageOverTime,
+        StatusKind::HealOverTime,
+        StatusKind::Shield,
+        StatusKind::SpeedBoost,
+    ];
+
+    pub fn from_u32(kind: u32) -> Option<Self> {
+        Self::ALL.get(kind as usize).copied()
+    }
+
+    pub fn stacking(self) -> Stacking {
+        match self {
+            StatusKind::Slow | StatusKind::SpeedBoost | StatusKind::Stun => Stacking::Strongest,
+            StatusKind::DamageOverTime | StatusKind::HealOverTime | StatusKind::Shield => {
+                Stacking::PerSource
+            }
+        }
+    }
+}
+
+/// An effect as written in the data files, e.g. the `status` of an ability.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct StatusDefinition {
+    pub kind: StatusKind,
+    #[serde(default)]
+    pub magnitude: f32,
+    /// Seconds the effect lasts.
+    pub duration: f32,
+}
+
+/// An effect on a unit, `source` being whoever applied it (an `EntityId` on the server).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatusEffect<S> {
+    pub kind: StatusKind,
+    pub magnitude: f32,
+    /// Seconds left.
+    pub duration: f32,
+    /// Seconds before a damage or healing over time changes health again, counted for each
+    /// effect so one applied just now doesn't tick along with the older ones.
+    pub next_tick: f32,
+    pub source: S,
+}
+
+/// Kinds, magnitudes, durations, next ticks and sources of a list of effects, as stored in
+/// the components of a unit.
+pub type StatusComponents<S> = (Vec<u32>, Vec<f32>, Vec<f32>, Vec<f32>, Vec<S>);
+
+/// The effects of one unit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusEffects<S> {
+    effects: Vec<StatusEffect<S>>,
+}
+
+impl<S> Default for StatusEffects<S> {
+    fn default() -> Self {
+        Self {
+            effects: Vec::new(),
+        }
+    }
+}
+
+impl<S: Copy + PartialEq> StatusEffects<S> {
+    /// Reads the parallel components of a unit back into a list. Entries of an unknown kind are dropped.
+    pub fn from_components(
+        kinds: &[u32],
+        magnitudes: &[f32],
+        durations: &[f32],
+        next_ticks: &[f32],
+        sources: &[S],
+    ) -> Self {
+        let effects = kinds
+            .iter()
+            .zip(magnitudes)
+            .zip(durations)
+            .zip(next_ticks)
+            .zip(sources)
+            .filter_map(|((((kind, magnitude), duration), next_tick), source)| {
+                Some(StatusEffect {
+                    kind: StatusKind::from_u32(*kind)?,
+                    magnitude: *magnitude,
+                    duration: *duration,
+                    next_tick: *next_tick,
+                    source: *source,
+                })
+            })
+            .collect();
+        Self { effects }
+    }
+
+    /// The parallel components of the list.
+    pub fn to_components(&self) -> StatusComponents<S> {
+        (
+            self.effects
+                .iter()
+                .map(|effect| effect.kind as u32)
+                .collect(),
+            self.magnitudes(),
+            self.effects.iter().map(|effect| effect.duration).collect(),
+            self.effects.iter().map(|effect| effect.next_tick).collect(),
+            self.effects.iter().map(|effect| effect.source).collect(),
+        )
+    }
+
+    /// Only the `status_magnitudes` component, which is all [`StatusEffects::absorb`] changes.
+    pub fn magnitudes(&self) -> Vec<f32> {
+        self.effects.iter().map(|effect| effect.magnitude).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &StatusEffect<S>> {
+        self.effects.iter()
+    }
+
+    pub fn apply(&mut self, effect: StatusEffect<S>) {
+        let existing = self.effects.iter().position(|other| {
+            other.kind == effect.kind
+                && match effect.kind.stacking() {
+                    Stacking::Strongest => true,
+                    Stacking::PerSource => other.source == effect.source,
+                }
+        });
+        let Some(existing) = existing else {
+            self.effects.push(effect);
+            return;
+        };
+
+        let other = &mut self.effects[existing];
+        let replace = match effect.kind.stacking() {
+            Stacking::Strongest => {
+                effect.magnitude > other.magnitude
+                    || (effect.magnitude == other.magnitude && effect.duration > other.duration)
+            }
+            Stacking::PerSource => true,
+        };
+        if replace {
+            // a refreshed effect keeps ticking when it did, reapplying it doesn't hold its next tick back
+            let next_tick = other.next_tick;
+            *other = StatusEffect {
+                next_tick,
+                ..effect
+            };
+        }
+    }
+
+    /// Counts `dt` seconds down, dropping the effects that ran out and the shields that broke.
+    /// Returns the health change of each damage or healing over time that ticked meanwhile,
+    /// negative for damage, with the source to credit for it.
+    pub fn tick(&mut self, dt: f32) -> Vec<(S, i32)> {
+        let mut changes = Vec::new();
+        for effect in &mut self.effects {
+            effect.duration -= dt;
+            let change = match effect.kind {
+                StatusKind::DamageOverTime => -effect.magnitude.round() as i32,
+                StatusKind::HealOverTime => effect.magnitude.round() as i32,
+                _ => continue,
+            };
+            effect.next_tick -= dt;
+            while effect.next_tick <= 0.0 {
+                changes.push((effect.source, change));
+                effect.next_tick += TICK_INTERVAL;
+            }
+        }
+        self.effects.retain(|effect| {
+            effect.duration > 0.0 && !(effect.kind == StatusKind::Shield && effect.magnitude <= 0.0)
+        });
+        changes
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.effects
+            .iter()
+            .any(|effect| effect.kind == StatusKind::Stun)
+    }
+
+    /// What the movement speed of the unit is multiplied by.
+    pub fn speed_multiplier(&self) -> f32 {
+        let slow = self
+            .effects
+            .iter()
+            .filter(|effect| effect.kind == StatusKind::Slow)
+            .map(|effect| effect.magnitude)
+            .fold(0.0, f32::max);
+        (1.0 - slow).clamp(0.0, 1.0)
+    }
+
+    /// What is added to the movement speed of the unit, before the slows multiply it.
+    pub fn speed_bonus(&self) -> f32 {
+        self.effects
+            .iter()
+            .filter(|effect| effect.kind == StatusKind::SpeedBoost)
+            .map(|effect| effect.magnitude)
+            .fold(0.0, f32::max)
+    }
+
+    /// Lets the shields absorb `damage`, oldest first, and returns what gets through.
+    pub fn absorb(&mut self, damage: i32) -> i32 {
+        let mut damage = damage as f32;
+        for effect in &mut self.effects {
+            if effect.kind != StatusKind::Shield || damage <= 0.0 {
+                continue;
+            }
+            let absorbed = effect.magnitude.min(damage);
+            effect.magnitude -= absorbed;
+            damage -= absorbed;
+        }
+        damage.round() as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(kind: StatusKind, magnitude: f32, duration: f32, source: u32) -> StatusEffect<u32> {
+        StatusEffect {
+            kind,
+            magnitude,
+            duration,
+            next_tick: TICK_INTERVAL,
+            source,
+        }
+    }
+
+    fn effects(list: &[StatusEffect<u32>]) -> StatusEffects<u32> {
+        let mut effects = StatusEffects::default();
+        for effect in list {
+            effects.apply(*effect);
+        }
+        effects
+    }
+
+    #[test]
+    fn strongest_slow_wins_whoever_applied_it() {
+        let mut slows = effects(&[effect(StatusKind::Slow, 0.3, 2.0, 1)]);
+        slows.apply(effect(StatusKind::Slow, 0.5, 1.0, 2));
+        assert_eq!(slows.iter().count(), 1);
+        assert_eq!(slows.speed_multiplier(), 0.5);
+
+        // a weaker one doesn't replace it, even if it lasts longer
+        slows.apply(effect(StatusKind::Slow, 0.2, 5.0, 3));
+        assert_eq!(slows.iter().next().unwrap().source, 2);
+
+        // as strong and longer refreshes it
+        slows.apply(effect(StatusKind::Slow, 0.5, 3.0, 1));
+        let slow = slows.iter().next().unwrap();
+        assert_eq!((slow.duration, slow.source), (3.0, 1));
+    }
+
+    #[test]
+    fn strongest_speed_boost_adds_to_the_speed() {
+        let mut boosts = effects(&[
+            effect(StatusKind::SpeedBoost, 1.8, 4.0, 1),
+            effect(StatusKind::Slow, 0.5, 4.0, 2),
+        ]);
+        boosts.apply(effect(StatusKind::SpeedBoost, 1.0, 6.0, 3));
+        assert_eq!(boosts.iter().count(), 2);
+        assert_eq!(boosts.speed_bonus(), 1.8);
+        // slows still apply on their own
+        assert_eq!(boosts.speed_multiplier(), 0.5);
+
+        boosts.tick(4.0);
+        assert_eq!(boosts.speed_bonus(), 0.0);
+        assert!(boosts.is_empty());
+    }
+
+    #[test]
+    fn per_source_effects_add_up_and_refresh() {
+        let mut dots = effects(&[
+            effect(StatusKind::DamageOverTime, 5.0, 3.0, 1),
+            effect(StatusKind::DamageOverTime, 2.0, 3.0, 2),
+        ]);
+        assert_eq!(dots.iter().count(), 2);
+
+        dots.tick(0.5);
+        dots.apply(effect(StatusKind::DamageOverTime, 4.0, 3.0, 1));
+        assert_eq!(dots.iter().count(), 2);
+        let refreshed = dots.iter().find(|effect| effect.source == 1).unwrap();
+        assert_eq!((refreshed.magnitude, refreshed.duration), (4.0, 3.0));
+        // it ticks when it would have, reapplying it doesn't hold the damage back
+        assert_eq!(refreshed.next_tick, TICK_INTERVAL - 0.5);
+    }
+
+    #[test]
+    fn shields_absorb_oldest_first() {
+        let mut shields = effects(&[
+            effect(StatusKind::Shield, 10.0, 5.0, 1),
+            effect(StatusKind::Shield, 10.0, 5.0, 2),
+            effect(StatusKind::Slow, 0.5, 5.0, 3),
+        ]);
+        assert_eq!(shields.absorb(4), 0);
+        assert_eq!(shields.magnitudes(), vec![6.0, 10.0, 0.5]);
+        assert_eq!(shields.absorb(20), 4);
+        assert_eq!(shields.magnitudes(), vec![0.0, 0.0, 0.5]);
+
+        // broken shields are gone at the next tick
+        shields.tick(0.1);
+        assert_eq!(shields.iter().count(), 1);
+        assert_eq!(StatusEffects::<u32>::default().absorb(7), 7);
+    }
+
+    #[test]
+    fn tick_counts_down_and_drops_what_ran_out() {
+        let mut list = effects(&[
+            effect(StatusKind::Stun, 0.0, 0.5, 1),
+            effect(StatusKind::Slow, 0.4, 2.0, 1),
+        ]);
+        list.tick(0.4);
+        assert!(list.is_stunned());
+        list.tick(0.2);
+        assert!(!list.is_stunned());
+        assert_eq!(list.speed_multiplier(), 0.6);
+        list.tick(2.0);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn each_periodic_effect_ticks_on_its_own_time() {
+        let mut list = effects(&[effect(StatusKind::DamageOverTime, 5.0, 3.5, 1)]);
+        assert_eq!(list.tick(0.6), vec![]);
+        list.apply(effect(StatusKind::HealOverTime, 3.0, 3.5, 2));
+
+        // the damage a second after it came, the healing a second after it did
+        assert_eq!(list.tick(0.4), vec![(1, -5)]);
+        assert_eq!(list.tick(0.6), vec![(2, 3)]);
+
+        // a long frame catches up with every tick it covered
+        assert_eq!(list.tick(2.0), vec![(1, -5), (1, -5), (2, 3), (2, 3)]);
+        // and the damage ran out while the healing, applied later, goes on
+        let left: Vec<_> = list.iter().map(|effect| effect.kind).collect();
+        assert_eq!(left, vec![StatusKind::HealOverTime]);
+    }
+}
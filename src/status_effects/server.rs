@@ -0,0 +1,127 @@
+use ambient_api::prelude::*;
+use amoba::status_effects::{StatusEffect, StatusEffects, StatusKind, TICK_INTERVAL};
+
+#[path = "../server_common.rs"]
+mod server_common;
+use server_common::{deal_damage, is_alive};
+
+#[main]
+pub fn main() {
+    // abilities, items and skillshots live in other modules, they put effects on units with this message
+    messages::ApplyStatusEffect::subscribe(|_, msg| {
+        apply_status_effect(msg);
+    });
+
+    status_effects_system();
+}
+
+fn apply_status_effect(msg: messages::ApplyStatusEffect) {
+    let kind = match StatusKind::from_u32(msg.kind) {
+        Some(kind) => kind,
+        None => {
+            println!("unknown status effect kind {}", msg.kind);
+            return;
+        }
+    };
+    if !is_alive(msg.unit) {
+        return;
+    }
+
+    let mut effects = effects_of(msg.unit);
+    effects.apply(StatusEffect {
+        kind,
+        magnitude: msg.magnitude,
+        duration: msg.duration,
+        next_tick: TICK_INTERVAL,
+        source: msg.source,
+    });
+    set_effects(msg.unit, &effects);
+}
+
+fn status_effects_system() {
+    query(components::status_kinds()).each_frame(|units| {
+        for (unit, _) in units {
+            // a hero comes back from the dead without the effects it died with
+            if !is_alive(unit) {
+                set_effects(unit, &StatusEffects::default());
+                continue;
+            }
+
+            let mut effects = effects_of(unit);
+            let changes = effects.tick(delta_time());
+            set_effects(unit, &effects);
+            // once the effects are written back, so the damage goes through the shields left
+            for (source, change) in changes {
+                if change < 0 {
+                    deal_damage(unit, -change, source);
+                } else {
+                    heal(unit, change);
+                }
+            }
+        }
+    });
+}
+
+fn effects_of(unit: EntityId) -> StatusEffects<EntityId> {
+    StatusEffects::from_components(
+        &entity::get_component(unit, components::status_kinds()).unwrap_or_default(),
+        &entity::get_component(unit, components::status_magnitudes()).unwrap_or_default(),
+        &entity::get_component(unit, components::status_durations()).unwrap_or_default(),
+        &entity::get_component(unit, components::status_next_ticks()).unwrap_or_default(),
+        &entity::get_component(unit, components::status_sources()).unwrap_or_default(),
+    )
+}
+
+// writes the effects back, along with what the movement and attack systems of the other modules read
+fn set_effects(unit: EntityId, effects: &StatusEffects<EntityId>) {
+    if effects.is_empty() {
+        entity::remove_component(unit, components::status_kinds());
+        entity::remove_component(unit, components::status_magnitudes());
+        entity::remove_component(unit, components::status_durations());
+        entity::remove_component(unit, components::status_next_ticks());
+        entity::remove_component(unit, components::status_sources());
+        entity::remove_component(unit, components::is_stunned());
+        entity::remove_component(unit, components::speed_multiplier());
+        entity::remove_component(unit, components::speed_bonus());
+        return;
+    }
+
+    let (kinds, magnitudes, durations, next_ticks, sources) = effects.to_components();
+    entity::add_component(unit, components::status_kinds(), kinds);
+    entity::add_component(unit, components::status_magnitudes(), magnitudes);
+    entity::add_component(unit, components::status_durations(), durations);
+    entity::add_component(unit, components::status_next_ticks(), next_ticks);
+    entity::add_component(unit, components::status_sources(), sources);
+
+    let is_stunned = entity::has_component(unit, components::is_stunned());
+    if effects.is_stunned() && !is_stunned {
+        entity::add_component(unit, components::is_stunned(), ());
+    } else if !effects.is_stunned() && is_stunned {
+        entity::remove_component(unit, components::is_stunned());
+    }
+
+    let speed_multiplier = effects.speed_multiplier();
+    if speed_multiplier < 1.0 {
+        entity::add_component(unit, components::speed_multiplier(), speed_multiplier);
+    } else {
+        entity::remove_component(unit, components::speed_multiplier());
+    }
+
+    let speed_bonus = effects.speed_bonus();
+    if speed_bonus > 0.0 {
+        entity::add_component(unit, components::speed_bonus(), speed_bonus);
+    } else {
+        entity::remove_component(unit, components::speed_bonus());
+    }
+}
+
+fn heal(unit: EntityId, amount: i32) {
+    if let Some(health) = entity::get_component(unit, components::health()) {
+        let max_health = entity::get_component(unit, components::max_health()).unwrap_or(health);
+        entity::set_component(
+            unit,
+            components::health(),
+            (health + amount).min(max_health),
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Steering for units that walk in groups, like creep waves.
+//!
+//! Each frame a unit heads for its target, slowing down as it arrives, while it is pushed
+//! away from the allies around it and from the obstacles in front of it. The pushes are
+//! added to where it wants to go, so a crowd flows around a blocked creep or a base instead
+//! of stopping as soon as two colliders touch.
+
+use glam::Vec2;
+use serde::Deserialize;
+
+/// How hard units push each other and the obstacles around them, the `[steering]` table of
+/// `data/creeps.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Steering {
+    /// Room kept between the colliders of two allies.
+    pub separation_distance: f32,
+    pub separation_weight: f32,
+    /// Room kept between a collider and an obstacle.
+    pub avoidance_distance: f32,
+    pub avoidance_weight: f32,
+    /// Distance to its target where a unit starts slowing down.
+    pub arrival_distance: f32,
+}
+
+/// Something to walk around.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Obstacle {
+    Circle { center: Vec2, radius: f32 },
+    Box { center: Vec2, size: Vec2 },
+}
+
+impl Obstacle {
+    /// The point of the obstacle's edge closest to `position`.
+    pub fn closest_point(&self, position: Vec2) -> Vec2 {
+        match *self {
+            Obstacle::Circle { center, radius } => {
+                center + (position - center).normalize_or_zero() * radius
+            }
+            Obstacle::Box { center, size } => {
+                center + (position - center).clamp(-size / 2., size / 2.)
+            }
+        }
+    }
+}
+
+/// A unit around the one being steered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Neighbour {
+    pub position: Vec2,
+    pub radius: f32,
+}
+
+impl Steering {
+    /// Units per second a unit of `radius` at `position` walks with, heading for `target`
+    /// at up to `speed`. Never faster than `speed`.
+    pub fn velocity(
+        &self,
+        position: Vec2,
+        radius: f32,
+        speed: f32,
+        target: Vec2,
+        allies: &[Neighbour],
+        obstacles: &[Obstacle],
+    ) -> Vec2 {
+        let to_target = target - position;
+        let direction = to_target.normalize_or_zero();
+        let arrival = match self.arrival_distance > 0. {
+            true => (to_target.length() / self.arrival_distance).min(1.),
+            false => 1.,
+        };
+        let seek = direction * speed * arrival;
+
+        let mut separation = Vec2::ZERO;
+        for ally in allies {
+            let away = position - ally.position;
+            let room = radius + ally.radius + self.separation_distance;
+            let distance = away.length();
+            if distance < room && distance > f32::EPSILON {
+                separation += away / distance * (1. - distance / room);
+            }
+        }
+
+        let mut avoidance = Vec2::ZERO;
+        for obstacle in obstacles {
+            let away = position - obstacle.closest_point(position);
+            let room = radius + self.avoidance_distance;
+            let distance = away.length();
+            if distance >= room || distance <= f32::EPSILON {
+                continue;
+            }
+            let away = away / distance;
+            // only what stands in front matters, and the unit slides along it rather than backing off
+            if away.dot(direction) >= 0. {
+                continue;
+            }
+            let along = away.perp() * away.perp().dot(direction).signum();
+            avoidance += (away + along) * (1. - distance / room);
+        }
+
+        (seek + (separation * self.separation_weight + avoidance * self.avoidance_weight) * speed)
+            .clamp_length_max(speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEERING: Steering = Steering {
+        separation_distance: 0.3,
+        separation_weight: 1.5,
+        avoidance_distance: 1.0,
+        avoidance_weight: 2.0,
+        arrival_distance: 2.0,
+    };
+    const RADIUS: f32 = 0.5;
+    const SPEED: f32 = 3.0;
+
+    fn velocity(
+        position: Vec2,
+        target: Vec2,
+        allies: &[Neighbour],
+        obstacles: &[Obstacle],
+    ) -> Vec2 {
+        STEERING.velocity(position, RADIUS, SPEED, target, allies, obstacles)
+    }
+
+    #[test]
+    fn overlapping_allies_push_apart() {
+        let (first, second) = (Vec2::new(0., 0.), Vec2::new(0.4, 0.1));
+        let neighbour = |position| Neighbour {
+            position,
+            radius: RADIUS,
+        };
+        // both standing where they want to be, only the push moves them
+        let first_velocity = velocity(first, first, &[neighbour(second)], &[]);
+        let second_velocity = velocity(second, second, &[neighbour(first)], &[]);
+
+        let apart = second - first;
+        assert!(first_velocity.dot(apart) < 0.);
+        assert!(second_velocity.dot(apart) > 0.);
+        assert!((first_velocity + second_velocity).length() < 1e-5);
+
+        // allies far enough apart leave each other alone
+        let far = Vec2::new(RADIUS * 2. + STEERING.separation_distance + 0.1, 0.);
+        assert_eq!(velocity(first, first, &[neighbour(far)], &[]), Vec2::ZERO);
+    }
+
+    #[test]
+    fn slides_along_a_box_in_the_way() {
+        let obstacle = Obstacle::Box {
+            center: Vec2::new(2., 0.),
+            size: Vec2::new(2., 2.),
+        };
+        let position = Vec2::new(0.2, 0.3);
+        let target = Vec2::new(10., 0.3);
+
+        let free = velocity(position, target, &[], &[]);
+        let steered = velocity(position, target, &[], &[obstacle]);
+        assert!(steered.x < free.x, "didn't slow down in front of the box");
+        assert!(steered.y.abs() > 0.1, "didn't turn: {steered}");
+        assert!(steered.length() <= SPEED + 1e-5);
+
+        // a box behind it doesn't matter
+        let behind = velocity(position, -target, &[], &[obstacle]);
+        assert_eq!(behind, velocity(position, -target, &[], &[]));
+    }
+
+    #[test]
+    fn slides_around_a_circle_in_the_way() {
+        let obstacle = Obstacle::Circle {
+            center: Vec2::new(1.5, 0.),
+            radius: 0.5,
+        };
+        let position = Vec2::new(0., 0.2);
+        let target = Vec2::new(10., 0.2);
+
+        let steered = velocity(position, target, &[], &[obstacle]);
+        assert!(steered.x < SPEED);
+        // around the side it is already on
+        assert!(
+            steered.y > 0.1,
+            "didn't turn away from the circle: {steered}"
+        );
+    }
+
+    #[test]
+    fn slows_down_when_arriving_and_never_goes_over_speed() {
+        let position = Vec2::ZERO;
+        let far = velocity(position, Vec2::new(10., 0.), &[], &[]);
+        assert!((far.length() - SPEED).abs() < 1e-5);
+
+        let close = velocity(position, Vec2::new(0.5, 0.), &[], &[]);
+        let expected = SPEED * 0.5 / STEERING.arrival_distance;
+        assert!((close.length() - expected).abs() < 1e-5, "{close}");
+        assert_eq!(velocity(position, position, &[], &[]), Vec2::ZERO);
+
+        // a crowd pushing from behind doesn't make it faster than its speed
+        let crowd: Vec<Neighbour> = (0..8)
+            .map(|index| Neighbour {
+                position: Vec2::new(-0.2, index as f32 * 0.1 - 0.4),
+                radius: RADIUS,
+            })
+            .collect();
+        let pushed = velocity(position, Vec2::new(0.5, 0.), &crowd, &[]);
+        assert!(pushed.length() <= SPEED + 1e-5);
+    }
+}
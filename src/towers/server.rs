@@ -0,0 +1,276 @@
+use ambient_api::{
+    components::core::{
+        physics::cube_collider,
+        primitives::cube,
+        rendering::{cast_shadows, color},
+        transform::{scale, translation},
+    },
+    concepts::make_transformable,
+    prelude::*,
+};
+use amoba::{
+    behaviour,
+    map_data::{MapData, TowerStats, TOWER_WIDTH},
+    rules::{self, TowerSlot},
+    spatial::SpatialGrid,
+    stats::Stat,
+};
+
+#[path = "../server_common.rs"]
+mod server_common;
+use server_common::{is_alive, stat};
+
+const MARS_TEAM: u32 = 0;
+const JUPYTER_TEAM: u32 = 1;
+
+const TOWER_HEIGHT: f32 = 3.0;
+
+// side of the cells of the grids towers look for enemies in, about their attack range
+const TOWER_GRID_CELL_SIZE: f32 = 5.0;
+
+#[main]
+pub fn main() {
+    let map = MapData::load();
+
+    // (team, lane) of every lane that has towers, a base can be hit once one of them has none left
+    let mut defended_lanes = Vec::new();
+    for (lane_index, lane) in map.lanes.iter().enumerate() {
+        if map.towers_of_lane(lane).next().is_some() {
+            defended_lanes.push((map.lane_team(lane), lane_index as u32));
+        }
+    }
+
+    spawn_towers(&map);
+
+    tower_protection_system(defended_lanes);
+    tower_attack_system(map.ground_size);
+    tower_death_system();
+
+    // destroyed towers are back for the next match
+    messages::MatchReset::subscribe(move |_, _| {
+        for (tower, _) in query(components::is_tower()).build().evaluate() {
+            entity::despawn(tower);
+        }
+        spawn_towers(&map);
+    });
+}
+
+fn spawn_towers(map: &MapData) {
+    for (lane_index, lane) in map.lanes.iter().enumerate() {
+        let team = map.lane_team(lane);
+        for (tier, tower) in map.towers_of_lane(lane).enumerate() {
+            spawn_tower(
+                &map.tower,
+                tower.position,
+                team,
+                lane_index as u32,
+                tier as u32,
+            );
+        }
+    }
+}
+
+fn spawn_tower(stats: &TowerStats, position: Vec2, team: u32, lane: u32, tier: u32) -> EntityId {
+    Entity::new()
+        .with_merge(make_transformable())
+        .with_default(cube())
+        .with_default(cast_shadows())
+        .with(cube_collider(), Vec3::ONE)
+        .with(scale(), vec3(TOWER_WIDTH, TOWER_WIDTH, TOWER_HEIGHT))
+        .with(translation(), position.extend(TOWER_HEIGHT / 2.))
+        .with(color(), team_color(team))
+        .with_default(components::is_tower())
+        .with(components::team(), team)
+        .with(components::lane(), lane)
+        .with(components::tower_tier(), tier)
+        .with(components::health(), stats.health)
+        .with(components::max_health(), stats.health)
+        .with(components::base_stats(), stats.base_stats().to_component())
+        .with(components::stats(), stats.base_stats().to_component())
+        .with(components::attack_cooldown(), 0.)
+        .with(components::projectile_speed(), stats.projectile_speed)
+        .with(components::bounty(), stats.bounty)
+        .spawn()
+}
+
+fn tower_protection_system(defended_lanes: Vec<(u32, u32)>) {
+    let bases_query = query(components::base_side()).build();
+
+    query((
+        components::is_tower(),
+        components::team(),
+        components::lane(),
+        components::tower_tier(),
+    ))
+    .each_frame(move |towers| {
+        let standing: Vec<TowerSlot> = towers
+            .iter()
+            .filter(|(tower, _)| is_alive(*tower))
+            .map(|(_, (_, team, lane, tier))| TowerSlot {
+                team: *team,
+                lane: *lane,
+                tier: *tier,
+            })
+            .collect();
+
+        // a tower is protected by the towers in front of it in its lane
+        for (tower, (_, team, lane, tier)) in &towers {
+            let slot = TowerSlot {
+                team: *team,
+                lane: *lane,
+                tier: *tier,
+            };
+            set_invulnerable(*tower, rules::is_tower_protected(slot, &standing));
+        }
+
+        // and a base by its towers, until one of its lanes has lost all of them
+        for (base, side) in bases_query.evaluate() {
+            set_invulnerable(
+                base,
+                rules::is_base_protected(side, &defended_lanes, &standing),
+            );
+        }
+    });
+}
+
+fn tower_attack_system(ground_size: f32) {
+    let creeps_query = query((components::is_creep(), components::team(), translation())).build();
+    let heroes_query = query((components::hero_model(), components::team())).build();
+    let hero_attacks_query = query((
+        components::hero_model(),
+        components::team(),
+        components::hero_attack_target(),
+    ))
+    .build();
+
+    query((
+        components::is_tower(),
+        components::team(),
+        translation(),
+        components::attack_cooldown(),
+    ))
+    .excludes(components::is_dead())
+    .excludes(components::is_stunned())
+    .each_frame(move |towers| {
+        let heroes = heroes_query.evaluate();
+        let hero_attacks = hero_attacks_query.evaluate();
+
+        // (unit, team) of every creep and hero, by where they stand
+        let mut creeps = SpatialGrid::new(ground_size, TOWER_GRID_CELL_SIZE);
+        creeps.extend(creeps_query.evaluate().into_iter().map(
+            |(creep, (_, creep_team, creep_position))| (creep_position.xy(), (creep, creep_team)),
+        ));
+        let mut hero_positions = SpatialGrid::new(ground_size, TOWER_GRID_CELL_SIZE);
+        hero_positions.extend(heroes.iter().filter_map(|(_, (hero, hero_team))| {
+            let hero_position = entity::get_component(*hero, translation())?;
+            Some((hero_position.xy(), (*hero, *hero_team)))
+        }));
+
+        for (tower, (_, team, position, attack_cooldown)) in towers {
+            let attack_range = stat(tower, Stat::AttackRange);
+            let in_range = |unit: EntityId| {
+                is_alive(unit)
+                    && entity::get_component(unit, translation()).map_or(false, |unit_position| {
+                        (unit_position.xy() - position.xy()).length() <= attack_range
+                    })
+            };
+
+            // an enemy hero hitting an allied hero under the tower draws its fire, whatever it was shooting at
+            let aggressor = hero_attacks
+                .iter()
+                .find(|(_, (attacker, attacker_team, victim))| {
+                    *attacker_team != team
+                        && heroes
+                            .iter()
+                            .any(|(_, (hero, hero_team))| hero == victim && *hero_team == team)
+                        && in_range(*attacker)
+                        && in_range(*victim)
+                })
+                .map(|(_, (attacker, _, _))| *attacker);
+
+            let current_target = entity::get_component(tower, components::tower_target());
+
+            // the same choice and cooldown as the towers of the simulation, which have no heroes to shoot
+            let action = behaviour::tower_action(
+                aggressor.or(current_target),
+                in_range,
+                || {
+                    nearest_enemy_in_range(&creeps, position, attack_range, team).or_else(|| {
+                        nearest_enemy_in_range(&hero_positions, position, attack_range, team)
+                    })
+                },
+                attack_cooldown,
+                stat(tower, Stat::AttackInterval),
+                delta_time(),
+            );
+
+            let Some(target) = action.target else {
+                entity::remove_component(tower, components::tower_target());
+                continue;
+            };
+            entity::add_component(tower, components::tower_target(), target);
+            entity::set_component(tower, components::attack_cooldown(), action.attack_cooldown);
+            if !action.attacks {
+                continue;
+            }
+
+            messages::SpawnProjectile::new(
+                tower,
+                target,
+                position + Vec3::Z * TOWER_HEIGHT / 2.,
+                entity::get_component(target, translation()).unwrap(),
+                entity::get_component(tower, components::projectile_speed()).unwrap(),
+                stat(tower, Stat::AttackDamage).round() as i32,
+                team,
+            )
+            .send_local_broadcast(false);
+        }
+    });
+}
+
+fn tower_death_system() {
+    query((components::is_tower(), components::health()))
+        .excludes(components::is_dead())
+        .each_frame(|towers| {
+            for (tower, (_, health)) in towers {
+                if health <= 0 {
+                    println!("tower {} destroyed", tower);
+                    let killer = entity::get_component(tower, components::last_attacker())
+                        .unwrap_or(EntityId::null());
+                    let bounty = entity::get_component(tower, components::bounty()).unwrap_or(0);
+                    messages::UnitKilled::new(tower, killer, bounty).send_local_broadcast(false);
+                    entity::despawn(tower);
+                }
+            }
+        });
+}
+
+fn nearest_enemy_in_range(
+    units: &SpatialGrid<(EntityId, u32)>,
+    position: Vec3,
+    range: f32,
+    team: u32,
+) -> Option<EntityId> {
+    units
+        .nearest(position.xy(), range, |(unit, unit_team)| {
+            rules::is_lane_enemy(team, *unit_team) && is_alive(*unit)
+        })
+        .map(|(_, (unit, _))| *unit)
+}
+
+fn set_invulnerable(unit: EntityId, invulnerable: bool) {
+    let is_invulnerable = entity::has_component(unit, components::is_invulnerable());
+    if invulnerable && !is_invulnerable {
+        entity::add_component(unit, components::is_invulnerable(), ());
+    } else if !invulnerable && is_invulnerable {
+        entity::remove_component(unit, components::is_invulnerable());
+    }
+}
+
+fn team_color(team: u32) -> Vec4 {
+    match team {
+        MARS_TEAM => vec4(1.0, 0.0, 0.1, 1.),
+        JUPYTER_TEAM => vec4(0., 0., 1., 1.),
+        _ => vec4(1., 1., 1., 1.),
+    }
+}
@@ -1,9 +1,48 @@
 use ambient_api::components::core::app::window_logical_size;
 use ambient_api::prelude::*;
+use amoba::{match_state::NO_WINNER, roster::HeroRoster, shop::Shop};
+
+const LEVEL_UP_NOTICE_DURATION: f32 = 3.0;
+
+// the abilities of a hero, in the order they are listed in data/heroes.toml
+const ABILITY_KEYS: [(KeyCode, &str); 4] = [
+    (KeyCode::Q, "Q"),
+    (KeyCode::W, "W"),
+    (KeyCode::E, "E"),
+    (KeyCode::R, "R"),
+];
+
 #[main]
 pub fn main() {
     // let now = Arc::new(Mutex::new(time()));
-    App.el().spawn_interactive();
+    App::el(HeroRoster::load()).spawn_interactive();
+    AbilityBar::el(HeroRoster::load()).spawn_interactive();
+    RespawnCountdown.el().spawn_interactive();
+    MatchResult.el().spawn_interactive();
+    HeroStatus.el().spawn_interactive();
+    ShopPanel::el(Shop::load()).spawn_interactive();
+
+    // kept in the client resources so the ui can read it every frame
+    messages::MatchEnded::subscribe(|_, msg| {
+        entity::add_component(entity::resources(), components::match_winner(), msg.winner);
+    });
+    messages::MatchReset::subscribe(|_, _| {
+        entity::remove_component(entity::resources(), components::match_winner());
+    });
+
+    messages::HeroLevelUp::subscribe(|_, msg| {
+        if msg.player_id != player::get_local() {
+            return;
+        }
+        entity::add_component(entity::resources(), components::level_up_notice(), msg.level);
+        run_async(async move {
+            sleep(LEVEL_UP_NOTICE_DURATION).await;
+            // a later level up keeps its own notice on screen
+            if entity::get_component(entity::resources(), components::level_up_notice()) == Some(msg.level) {
+                entity::remove_component(entity::resources(), components::level_up_notice());
+            }
+        });
+    });
 
     ambient_api::messages::Frame::subscribe(move |_| {
         let player_id = player::get_local();
@@ -46,24 +85,58 @@ pub fn main() {
             }
             .send_server_unreliable();
         }
+
+        if delta.mouse_buttons.contains(&MouseButton::Right) {
+            // attack whatever is under the cursor
+            let cam = entity::get_component(player_id, components::cam()).unwrap();
+            let ray = camera::screen_position_to_world_ray(cam, mouse_pos);
+            messages::MouseRightClick {
+                ray_origin: ray.origin,
+                ray_dir: ray.dir,
+                player_id,
+            }
+            .send_server_unreliable();
+        }
+
+        // abilities are aimed at whatever is under the cursor, the server sorts out what they hit
+        for (ability, (key, _)) in ABILITY_KEYS.iter().enumerate() {
+            if delta.keys.contains(key) {
+                let cam = entity::get_component(player_id, components::cam()).unwrap();
+                let ray = camera::screen_position_to_world_ray(cam, mouse_pos);
+                messages::CastAbility {
+                    player_id,
+                    ability: ability as u32,
+                    ray_origin: ray.origin,
+                    ray_dir: ray.dir,
+                }
+                .send_server_reliable();
+            }
+        }
     });
 }
 
 #[element_component]
-fn App(hooks: &mut Hooks) -> Element {
+fn App(hooks: &mut Hooks, roster: HeroRoster) -> Element {
     let (screen, set_screen) = hooks.use_state(None);
+    // shown until the server gives us a hero, and again once the match is reset
     let (show, set_show) = hooks.use_state(true);
+    hooks.use_frame(move |w| {
+        let local = player::get_local();
+        set_show(!w.has_component(local, components::hero_model()));
+    });
 
-    let f = FocusRoot::el([PageScreen::el([
-        ScreenContainer(screen).el(),
-        // FlowColumn::el([
-        Text::el("Select team:").with_margin_even(10.),
-        Button::new("Join Team Mars", {
+    // one "Join Team" button per team of the roster
+    let team_buttons = roster
+        .teams
+        .iter()
+        .enumerate()
+        .map(|(team, team_name)| {
+            let roster = roster.clone();
             let set_screen = set_screen.clone();
-            let set_show = set_show.clone();
-            let show = show.clone();
-            move |_| {
-                set_screen(Some(TeamMars::el(
+            Button::new(format!("Join Team {team_name}"), move |_| {
+                set_screen(Some(TeamHeroes::el(
+                    roster.clone(),
+                    team as u32,
                     cb({
                         let set_screen = set_screen.clone();
                         move || {
@@ -71,43 +144,28 @@ fn App(hooks: &mut Hooks) -> Element {
                         }
                     }),
                     cb({
-                        let set_show = set_show.clone();
-                        let show = show.clone();
+                        let set_screen = set_screen.clone();
                         move |r| {
                             let player_id = player::get_local();
                             messages::ChooseRole::new(player_id, r).send_server_reliable();
-                            set_show(!show);
+                            set_screen(None);
                         }
                     }),
                 )))
-            }
-        })
-        .el()
-        .with_margin_even(10.),
-        Button::new("Join Team Jupiter", move |_| {
-            set_screen(Some(TeamJupiter::el(
-                cb({
-                    let set_screen = set_screen.clone();
-                    move || {
-                        set_screen(None);
-                    }
-                }),
-                cb({
-                    let set_show = set_show.clone();
-                    let show = show.clone();
-                    move |r| {
-                        let player_id = player::get_local();
-                        messages::ChooseRole::new(player_id, r).send_server_reliable();
-                        // entity::add_component(player_id, components::role(), r);
-                        set_show(!show);
-                    }
-                }),
-            )))
-        })
-        .el()
-        .with_margin_even(10.),
-        // ]),
-    ])]);
+            })
+            .el()
+            .with_margin_even(10.)
+        });
+
+    let f = FocusRoot::el([PageScreen::el(
+        [
+            ScreenContainer(screen).el(),
+            Text::el("Select team:").with_margin_even(10.),
+        ]
+        .into_iter()
+        .chain(team_buttons)
+        .collect::<Vec<_>>(),
+    )]);
     if show {
         f
     } else {
@@ -116,49 +174,223 @@ fn App(hooks: &mut Hooks) -> Element {
 }
 
 #[element_component]
-fn TeamMars(
-    hooks: &mut Hooks,
-    on_back: Cb<dyn Fn() + Sync + Send>,
-    decision: Cb<dyn Fn(u32) + Sync + Send>,
-) -> Element {
-    let (screen, set_screen) = hooks.use_state(None);
-    let (id, _) = hooks.use_state_with(|_| random::<u32>());
-    PageScreen::el([
-        ScreenContainer(screen).el(),
-        // Text::el(format!("SubScreen {id}")),
-        Button::new("Back", move |_| on_back())
-            .el()
-            .with_margin_even(10.),
-        Button::new("Mars Hero 1", {
-            // let set_screen = set_screen.clone();
-            move |world| {
-                decision(0);
-            }
+fn RespawnCountdown(hooks: &mut Hooks) -> Element {
+    let (respawn_timer, set_respawn_timer) = hooks.use_state(None);
+    hooks.use_frame(move |w| {
+        let local = player::get_local();
+        set_respawn_timer(w.get(local, components::respawn_timer()).ok());
+    });
+
+    if let Some(seconds) = respawn_timer {
+        WindowSized::el([Dock::el([Text::el(format!(
+            "You died! Respawning in {}",
+            seconds.ceil() as u32
+        ))
+        .with_margin_even(10.)])])
+    } else {
+        Element::new()
+    }
+}
+
+#[element_component]
+fn HeroStatus(hooks: &mut Hooks) -> Element {
+    let (status, set_status) = hooks.use_state(None);
+    let (level_up, set_level_up) = hooks.use_state(None);
+    hooks.use_frame(move |w| {
+        let local = player::get_local();
+        // only shown once the player is in the game with a hero
+        let level = w.get(local, components::level()).ok();
+        let gold = w.get(local, components::gold()).ok();
+        set_status(level.zip(gold));
+        set_level_up(w.get(entity::resources(), components::level_up_notice()).ok());
+    });
+
+    let mut lines = Vec::new();
+    if let Some((level, gold)) = status {
+        lines.push(Text::el(format!("Level {} | Gold: {}", level, gold)).with_margin_even(10.));
+    }
+    if let Some(level) = level_up {
+        lines.push(Text::el(format!("Level up! You reached level {}", level)).with_margin_even(10.));
+    }
+
+    if lines.is_empty() {
+        Element::new()
+    } else {
+        WindowSized::el([Dock::el(lines)])
+    }
+}
+
+#[element_component]
+fn AbilityBar(hooks: &mut Hooks, roster: HeroRoster) -> Element {
+    let (state, set_state) = hooks.use_state(None);
+    hooks.use_frame(move |w| {
+        let local = player::get_local();
+        // only shown once the player is in the game with a hero
+        let role = w.get(local, components::role()).ok();
+        let mana = w.get(local, components::mana()).ok();
+        let max_mana = w.get(local, components::max_mana()).ok();
+        let cooldowns = w.get(local, components::ability_cooldowns()).ok();
+        set_state(role.zip(mana).zip(max_mana).zip(cooldowns));
+    });
+
+    let (((role, mana), max_mana), cooldowns): (((u32, f32), f32), Vec<f32>) = match state {
+        Some(state) => state,
+        None => return Element::new(),
+    };
+    let hero = match roster.hero(role) {
+        Some(hero) => hero,
+        None => return Element::new(),
+    };
+
+    let abilities = hero
+        .abilities
+        .iter()
+        .zip(ABILITY_KEYS)
+        .zip(cooldowns)
+        .map(|((ability, (_, key)), cooldown)| {
+            let status = if cooldown > 0.0 {
+                format!("{:.1}s", cooldown)
+            } else if mana < ability.mana_cost {
+                "no mana".to_string()
+            } else {
+                "ready".to_string()
+            };
+            Text::el(format!(
+                "[{}] {} ({} mana) - {}",
+                key, ability.name, ability.mana_cost, status
+            ))
+            .with_margin_even(5.)
+        });
+
+    WindowSized::el([Dock::el(
+        [Text::el(format!("Mana: {} / {}", mana as u32, max_mana as u32)).with_margin_even(10.)]
+            .into_iter()
+            .chain(abilities)
+            .collect::<Vec<_>>(),
+    )])
+}
+
+#[element_component]
+fn ShopPanel(hooks: &mut Hooks, shop: Shop) -> Element {
+    let (open, set_open) = hooks.use_state(false);
+    let (inventory, set_inventory) = hooks.use_state(None);
+    hooks.use_frame(move |w| {
+        let local = player::get_local();
+        set_inventory(w.get(local, components::inventory()).ok());
+    });
+
+    // no hero, no inventory, no shop
+    let inventory: Vec<u32> = match inventory {
+        Some(inventory) => inventory,
+        None => return Element::new(),
+    };
+
+    let toggle = Button::new(if open { "Close shop" } else { "Shop" }, move |_| {
+        set_open(!open)
+    })
+    .el()
+    .with_margin_even(10.);
+    if !open {
+        return WindowSized::el([Dock::el([toggle])]);
+    }
+
+    // the server checks the hero is close to its base and can afford the item
+    let item_buttons = shop.items.iter().enumerate().map(|(item_id, item)| {
+        Button::new(format!("{} ({} gold)", item.name, item.cost), move |_| {
+            messages::BuyItem::new(player::get_local(), item_id as u32).send_server_reliable();
         })
         .el()
-        .with_margin_even(10.),
-    ])
+        .with_margin_even(5.)
+    });
+
+    let slots = inventory.iter().enumerate().map(|(slot, item_id)| {
+        let slot = slot as u32;
+        match shop.item(*item_id) {
+            Some(item) => {
+                let mut row = vec![
+                    Text::el(item.name.clone()).with_margin_even(5.),
+                    Button::new(format!("Sell ({})", shop.sell_price(item)), move |_| {
+                        messages::SellItem::new(player::get_local(), slot).send_server_reliable();
+                    })
+                    .el()
+                    .with_margin_even(5.),
+                ];
+                if item.active.is_some() {
+                    row.push(
+                        Button::new("Use", move |_| {
+                            messages::UseItem::new(player::get_local(), slot)
+                                .send_server_reliable();
+                        })
+                        .el()
+                        .with_margin_even(5.),
+                    );
+                }
+                FlowRow::el(row)
+            }
+            None => Text::el("(empty)").with_margin_even(5.),
+        }
+    });
+
+    WindowSized::el([Dock::el(
+        [toggle, Text::el("Items:").with_margin_even(5.)]
+            .into_iter()
+            .chain(item_buttons)
+            .chain([Text::el("Inventory:").with_margin_even(5.)])
+            .chain(slots)
+            .collect::<Vec<_>>(),
+    )])
+}
+
+#[element_component]
+fn MatchResult(hooks: &mut Hooks) -> Element {
+    let (result, set_result) = hooks.use_state(None);
+    hooks.use_frame(move |w| {
+        let local = player::get_local();
+        let winner = w.get(entity::resources(), components::match_winner()).ok();
+        let team = w.get(local, components::team()).ok();
+        set_result(winner.map(|winner| match winner {
+            NO_WINNER => "Draw!",
+            _ if team == Some(winner) => "Victory!",
+            _ => "Defeat!",
+        }));
+    });
+
+    match result {
+        Some(result) => WindowSized::el([Dock::el([
+            Text::el(result).with_margin_even(10.),
+            Text::el("A new match starts soon").with_margin_even(10.),
+        ])]),
+        None => Element::new(),
+    }
 }
 
 #[element_component]
-fn TeamJupiter(
+fn TeamHeroes(
     hooks: &mut Hooks,
+    roster: HeroRoster,
+    team: u32,
     on_back: Cb<dyn Fn() + Sync + Send>,
     decision: Cb<dyn Fn(u32) + Sync + Send>,
 ) -> Element {
-    let (screen, set_screen) = hooks.use_state(None);
-    // let (id, _) = hooks.use_state_with(|_| random::<u32>());
-    PageScreen::el([
-        ScreenContainer(screen).el(),
-        // Text::el(format!("SubScreen {id}")),
-        Button::new("Back", move |_| on_back())
+    let (screen, _) = hooks.use_state(None);
+
+    // one button per hero of this team, sending the hero's role when picked
+    let hero_buttons = roster.heroes_of_team(team).map(|(role, hero)| {
+        let decision = decision.clone();
+        Button::new(hero.name.clone(), move |_| decision(role))
             .el()
-            .with_margin_even(10.),
-        Button::new("Jupiter Hero A", {
-            // let set_screen = set_screen.clone();
-            move |_| decision(1)
-        })
-        .el()
-        .with_margin_even(10.),
-    ])
+            .with_margin_even(10.)
+    });
+
+    PageScreen::el(
+        [
+            ScreenContainer(screen).el(),
+            Button::new("Back", move |_| on_back())
+                .el()
+                .with_margin_even(10.),
+        ]
+        .into_iter()
+        .chain(hero_buttons)
+        .collect::<Vec<_>>(),
+    )
 }
@@ -12,7 +12,45 @@ pub fn main() {
         messages::MouseLeftClick { ..msg }.send_local_broadcast(false);
     });
 
+    // the attack order is for the hero of whoever clicked, not whoever the client names
+    messages::MouseRightClick::subscribe(|source, msg| {
+        let Some(player_id) = source.client_entity_id() else {
+            return;
+        };
+        messages::MouseRightClick { player_id, ..msg }.send_local_broadcast(false);
+    });
+
     messages::CamMove::subscribe(|_, msg| {
         messages::CamMove { ..msg }.send_local_broadcast(false);
     });
+
+    // a client could name any player in these, the shop acts for whoever sent the message
+    messages::BuyItem::subscribe(|source, msg| {
+        let Some(player_id) = source.client_entity_id() else {
+            return;
+        };
+        messages::BuyItem { player_id, ..msg }.send_local_broadcast(false);
+    });
+
+    messages::SellItem::subscribe(|source, msg| {
+        let Some(player_id) = source.client_entity_id() else {
+            return;
+        };
+        messages::SellItem { player_id, ..msg }.send_local_broadcast(false);
+    });
+
+    messages::UseItem::subscribe(|source, msg| {
+        let Some(player_id) = source.client_entity_id() else {
+            return;
+        };
+        messages::UseItem { player_id, ..msg }.send_local_broadcast(false);
+    });
+
+    // same for abilities, the caster is whoever sent the message
+    messages::CastAbility::subscribe(|source, msg| {
+        let Some(player_id) = source.client_entity_id() else {
+            return;
+        };
+        messages::CastAbility { player_id, ..msg }.send_local_broadcast(false);
+    });
 }
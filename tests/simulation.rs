@@ -0,0 +1,163 @@
+//! Whole lanes played by the headless simulation, with the shipped map and creeps.
+//!
+//! Run with `cargo test --test simulation`. Nothing here needs the engine.
+
+use amoba::{
+    creeps::{CreepRoster, TIME_TO_FIRST_WAVE},
+    map_data::MapData,
+    simulation::{Event, Outcome, Simulation, UnitId, UnitKind, TIME_STEP},
+};
+
+const MARS_TEAM: u32 = 0;
+const JUPYTER_TEAM: u32 = 1;
+
+fn simulation(map: &MapData) -> Simulation {
+    Simulation::new(map, &CreepRoster::load())
+}
+
+fn kills(
+    simulation: &Simulation,
+) -> impl Iterator<Item = (UnitId, UnitKind, u32, Option<UnitId>, f32)> + '_ {
+    simulation.events().iter().filter_map(|event| match *event {
+        Event::Killed {
+            unit,
+            kind,
+            team,
+            killer,
+            time,
+            ..
+        } => Some((unit, kind, team, killer, time)),
+        Event::WaveSent { .. } => None,
+    })
+}
+
+#[test]
+fn waves_leave_on_schedule() {
+    let map = MapData::load();
+    let interval = CreepRoster::load().waves.interval;
+    let mut simulation = simulation(&map);
+    simulation.run_for(TIME_TO_FIRST_WAVE + 2. * interval + 1.);
+
+    let waves: Vec<(u32, f32)> = simulation
+        .events()
+        .iter()
+        .filter_map(|event| match *event {
+            Event::WaveSent { wave, time } => Some((wave, time)),
+            Event::Killed { .. } => None,
+        })
+        .collect();
+    assert_eq!(waves.len(), 3);
+    for (wave, time) in waves {
+        // the timer is looked at once a step and adds up rounding errors, so each wave can leave a
+        // few steps later than the one before
+        let expected = TIME_TO_FIRST_WAVE + wave as f32 * interval;
+        let late = (wave + 1) as f32 * 3. * TIME_STEP;
+        assert!(
+            time >= expected && time - expected <= late,
+            "wave {wave} left at {time}s instead of {expected}s"
+        );
+    }
+}
+
+#[test]
+fn same_match_plays_out_the_same() {
+    let map = MapData::load();
+    let mut first = simulation(&map);
+    let mut second = simulation(&map);
+    first.run_for(180.);
+    second.run_for(180.);
+
+    assert!(kills(&first).count() > 0);
+    assert_eq!(first.events(), second.events());
+    assert_eq!(first.units(), second.units());
+    assert_eq!(first.projectiles(), second.projectiles());
+}
+
+#[test]
+fn first_waves_meet_and_fight() {
+    let map = MapData::load();
+    let mut simulation = simulation(&map);
+    simulation.run_for(60.);
+
+    for team in [MARS_TEAM, JUPYTER_TEAM] {
+        let lost = kills(&simulation).filter(|(_, kind, unit_team, _, _)| {
+            *unit_team == team && matches!(kind, UnitKind::Creep(_))
+        });
+        assert!(
+            lost.count() > 0,
+            "team {team} lost no creep in the first minute"
+        );
+    }
+    // no tower falls to the first waves
+    assert!(kills(&simulation).all(|(_, kind, _, _, _)| matches!(kind, UnitKind::Creep(_))));
+}
+
+#[test]
+fn full_match_ends_with_towers_falling_in_order() {
+    let map = MapData::load();
+    let mut simulation = simulation(&map);
+    simulation.run_for(15. * 60.);
+
+    let winner = match simulation.outcome() {
+        Some(Outcome::Won(team)) => team,
+        outcome => panic!("the match ended in {outcome:?} after 15 minutes"),
+    };
+    let loser = 1 - winner;
+
+    // towers of a lane fall outermost first
+    let mut fallen: Vec<(u32, u32, u32)> = Vec::new();
+    for (_, kind, team, _, _) in kills(&simulation) {
+        if let UnitKind::Tower { lane, tier } = kind {
+            let in_front = fallen
+                .iter()
+                .filter(|(fallen_team, fallen_lane, _)| {
+                    *fallen_team == team && *fallen_lane == lane
+                })
+                .count();
+            assert_eq!(
+                in_front, tier as usize,
+                "tower {tier} of lane {lane} fell before the ones in front of it"
+            );
+            fallen.push((team, lane, tier));
+        }
+    }
+
+    // and the base only once a lane of its team lost all its towers
+    let (_, _, _, _, base_time) = kills(&simulation)
+        .find(|(_, kind, team, _, _)| *kind == UnitKind::Base && *team == loser)
+        .unwrap();
+    let open_lane = map.lanes.iter().enumerate().any(|(index, lane)| {
+        let towers_down = kills(&simulation)
+            .filter(|(_, kind, _, _, time)| {
+                *time <= base_time
+                    && matches!(kind, UnitKind::Tower { lane: tower_lane, .. } if *tower_lane == index as u32)
+            })
+            .count();
+        map.lane_team(lane) == loser && towers_down == map.towers_of_lane(lane).count()
+    });
+    assert!(
+        open_lane,
+        "the base of team {loser} fell while all its lanes had towers"
+    );
+}
+
+#[test]
+fn team_without_towers_loses() {
+    let mut map = MapData::load();
+    let jupiter_lanes: Vec<String> = map
+        .lanes
+        .iter()
+        .filter(|lane| map.lane_team(lane) == JUPYTER_TEAM)
+        .map(|lane| lane.name.clone())
+        .collect();
+    map.towers
+        .retain(|tower| !jupiter_lanes.contains(&tower.lane));
+
+    let mut simulation = simulation(&map);
+    simulation.run_for(5. * 60.);
+
+    assert_eq!(simulation.outcome(), Some(Outcome::Won(MARS_TEAM)));
+    // no Mars tower had to fall for it
+    assert!(kills(&simulation)
+        .all(|(_, kind, team, _, _)| team == JUPYTER_TEAM || matches!(kind, UnitKind::Creep(_))));
+}